@@ -0,0 +1,69 @@
+use xcb::base::*;
+use xcb::xproto;
+
+use err::*;
+
+/// Define the set of atoms interned at startup.
+macro_rules! atoms {
+    ($($field:ident => $name:expr,)*) => {
+        /// The atoms used by the window manager that are not predefined by the core protocol.
+        #[derive(Clone, Copy, Debug)]
+        pub struct Atoms {
+            $(pub $field: xproto::Atom,)*
+        }
+
+        impl Atoms {
+            /// Intern all atoms, sending all requests before waiting for any reply.
+            pub fn intern(con: &Connection) -> WmResult<Atoms> {
+                $(let $field = xproto::intern_atom(con, false, $name);)*
+
+                Ok(Atoms {
+                    $($field: $field
+                        .get_reply()
+                        .map_err(|_| XError::CouldNotInternAtom($name).wrap())?
+                        .atom(),)*
+                })
+            }
+        }
+    };
+}
+
+atoms! {
+    utf8_string => "UTF8_STRING",
+    wm_protocols => "WM_PROTOCOLS",
+    wm_delete_window => "WM_DELETE_WINDOW",
+    wm_take_focus => "WM_TAKE_FOCUS",
+    net_supported => "_NET_SUPPORTED",
+    net_supporting_wm_check => "_NET_SUPPORTING_WM_CHECK",
+    net_client_list => "_NET_CLIENT_LIST",
+    net_client_list_stacking => "_NET_CLIENT_LIST_STACKING",
+    net_active_window => "_NET_ACTIVE_WINDOW",
+    net_close_window => "_NET_CLOSE_WINDOW",
+    net_moveresize_window => "_NET_MOVERESIZE_WINDOW",
+    net_number_of_desktops => "_NET_NUMBER_OF_DESKTOPS",
+    net_desktop_names => "_NET_DESKTOP_NAMES",
+    net_current_desktop => "_NET_CURRENT_DESKTOP",
+    net_wm_desktop => "_NET_WM_DESKTOP",
+    net_wm_name => "_NET_WM_NAME",
+    net_wm_pid => "_NET_WM_PID",
+    net_wm_strut => "_NET_WM_STRUT",
+    net_wm_strut_partial => "_NET_WM_STRUT_PARTIAL",
+    net_wm_window_type => "_NET_WM_WINDOW_TYPE",
+    net_wm_window_type_desktop => "_NET_WM_WINDOW_TYPE_DESKTOP",
+    net_wm_window_type_dock => "_NET_WM_WINDOW_TYPE_DOCK",
+    net_wm_window_type_toolbar => "_NET_WM_WINDOW_TYPE_TOOLBAR",
+    net_wm_window_type_menu => "_NET_WM_WINDOW_TYPE_MENU",
+    net_wm_window_type_utility => "_NET_WM_WINDOW_TYPE_UTILITY",
+    net_wm_window_type_splash => "_NET_WM_WINDOW_TYPE_SPLASH",
+    net_wm_window_type_dialog => "_NET_WM_WINDOW_TYPE_DIALOG",
+    net_wm_window_type_notification => "_NET_WM_WINDOW_TYPE_NOTIFICATION",
+    net_wm_window_type_normal => "_NET_WM_WINDOW_TYPE_NORMAL",
+    net_wm_state => "_NET_WM_STATE",
+    net_wm_state_modal => "_NET_WM_STATE_MODAL",
+    net_wm_state_sticky => "_NET_WM_STATE_STICKY",
+    net_wm_state_hidden => "_NET_WM_STATE_HIDDEN",
+    net_wm_state_fullscreen => "_NET_WM_STATE_FULLSCREEN",
+    net_wm_state_above => "_NET_WM_STATE_ABOVE",
+    net_wm_state_below => "_NET_WM_STATE_BELOW",
+    net_wm_state_demands_attention => "_NET_WM_STATE_DEMANDS_ATTENTION",
+}
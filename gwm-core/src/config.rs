@@ -1,5 +1,108 @@
-#[derive(Debug, PartialEq, Eq, Hash)]
+//! Compile-time configuration.
+//!
+//! Changing any of these values, including the appearance of title strips, requires
+//! rebuilding gwm. Only the rules applied to new clients are read from the configuration
+//! file (`~/.gwmrc`) at startup, see the `rules` module.
+
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Tag {
     Work(i8),
     NonWork,
 }
+
+impl fmt::Display for Tag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Tag::Work(n) => write!(f, "{}", n),
+            Tag::NonWork => write!(f, "nonwork"),
+        }
+    }
+}
+
+/// All tags, in the order they are presented to the user.
+pub const TAGS: &[Tag] = &[
+    Tag::Work(1),
+    Tag::Work(2),
+    Tag::Work(3),
+    Tag::Work(4),
+    Tag::Work(5),
+    Tag::NonWork,
+];
+
+/// The time in milliseconds after which clients asked to close are killed, 0 to never kill.
+pub const CLOSE_TIMEOUT: u64 = 2000;
+
+/// Whether the client under the mouse pointer is focused when the pointer enters it.
+pub const FOCUS_FOLLOWS_MOUSE: bool = false;
+
+/// Whether to expose the tags as EWMH desktops, for pagers that don't know about tags.
+pub const EWMH_DESKTOPS: bool = false;
+
+/// The tags shown on each screen at startup.
+pub const INITIAL_TAGS: &[Tag] = &[Tag::Work(1)];
+
+/// Whether the size hints of clients are honored when tiling them.
+#[derive(Clone, Copy, Debug)]
+pub struct SizeHintsConfig {
+    /// Whether size hints are honored by default.
+    pub honor: bool,
+    /// The `WM_CLASS` class names of clients for which the default is inverted.
+    pub exceptions: &'static [&'static str],
+}
+
+impl SizeHintsConfig {
+    /// Check whether the size hints of a client with the given class name are honored.
+    pub fn honored_for(&self, class: Option<&str>) -> bool {
+        let exception = class.is_some_and(|c| self.exceptions.contains(&c));
+
+        self.honor != exception
+    }
+}
+
+pub const SIZE_HINTS: SizeHintsConfig = SizeHintsConfig {
+    honor: true,
+    exceptions: &[],
+};
+
+/// Appearance of the title strips drawn above tabbed and stacked containers.
+#[derive(Clone, Copy, Debug)]
+pub struct TitleConfig {
+    /// The X core font used to draw titles.
+    pub font: &'static str,
+    /// Vertical padding around the title text, in pixels.
+    pub padding: u32,
+    /// Text color of unfocused titles.
+    pub fg: u32,
+    /// Background color of unfocused titles.
+    pub bg: u32,
+    /// Text color of the focused title.
+    pub focused_fg: u32,
+    /// Background color of the focused title.
+    pub focused_bg: u32,
+}
+
+/// The appearance of title strips, fixed at compile time like the rest of this module.
+pub const TITLES: TitleConfig = TitleConfig {
+    font: "fixed",
+    padding: 2,
+    fg: 0x888888,
+    bg: 0x222222,
+    focused_fg: 0xffffff,
+    focused_bg: 0x285577,
+};
+
+/// A named scratchpad, holding hidden clients that can be shown on demand.
+#[derive(Clone, Copy, Debug)]
+pub struct ScratchpadConfig {
+    /// The name of the scratchpad.
+    pub name: &'static str,
+    /// The `WM_CLASS` class names of clients moved to the scratchpad when they appear.
+    pub classes: &'static [&'static str],
+}
+
+pub const SCRATCHPADS: &[ScratchpadConfig] = &[
+    ScratchpadConfig { name: "terminal", classes: &["scratchterm"] },
+    ScratchpadConfig { name: "notes", classes: &["notes"] },
+];
@@ -0,0 +1,401 @@
+use regex::Regex;
+
+use xcb::xproto;
+
+use config::{self, Tag};
+use properties::{ClientProperties, WindowType};
+use tree::{Client, ClientHierarchy};
+
+/// A pattern matching a string property.
+#[derive(Debug)]
+pub enum Pattern {
+    /// The property has to equal the string.
+    Exact(String),
+    /// The property has to match the regular expression.
+    Regex(Regex),
+}
+
+impl Pattern {
+    /// Parse a pattern, which is a regular expression if prefixed with `~`.
+    pub fn parse(pattern: &str) -> Result<Pattern, String> {
+        if let Some(regex) = pattern.strip_prefix('~') {
+            Regex::new(regex)
+                .map(Pattern::Regex)
+                .map_err(|e| format!("invalid regular expression {}: {}", regex, e))
+        } else {
+            Ok(Pattern::Exact(pattern.to_owned()))
+        }
+    }
+
+    /// Check whether a string matches the pattern.
+    pub fn matches(&self, value: &str) -> bool {
+        match self {
+            Pattern::Exact(s) => s == value,
+            Pattern::Regex(r) => r.is_match(value),
+        }
+    }
+}
+
+/// Conditions selecting clients, used by rules and to target control requests.
+///
+/// All conditions given have to be met.
+#[derive(Debug, Default)]
+pub struct Criteria {
+    /// The client's window.
+    pub window: Option<xproto::Window>,
+    /// The `WM_CLASS` class name.
+    pub class: Option<Pattern>,
+    /// The `WM_CLASS` instance name.
+    pub instance: Option<Pattern>,
+    /// The title.
+    pub title: Option<Pattern>,
+    /// A window type the client has.
+    pub window_type: Option<WindowType>,
+    /// Whether the client is transient for another window.
+    pub transient: Option<bool>,
+    /// A tag the client has.
+    pub tag: Option<Tag>,
+    /// Whether the client is urgent.
+    pub urgent: Option<bool>,
+}
+
+impl Criteria {
+    /// Parse criteria given in the form `[class="Firefox" title="~.*Meet.*" tag=3]`.
+    ///
+    /// Values can be quoted, and `class`, `instance` and `title` are regular expressions
+    /// if prefixed with `~`. The other keys are `id` (a window, in decimal or prefixed with
+    /// `0x`), `window_type` (such as `dialog`), `transient`, `tag` (a tag name) and `urgent`.
+    pub fn parse(input: &str) -> Result<Criteria, String> {
+        let input = input.trim();
+
+        if !input.starts_with('[') || !input.ends_with(']') || input.len() < 2 {
+            return Err(format!("criteria have to be enclosed in brackets: {}", input));
+        }
+
+        let mut criteria = Criteria::default();
+        let mut chars = input[1..input.len() - 1].chars().peekable();
+
+        loop {
+            while chars.peek().is_some_and(|c| c.is_whitespace()) {
+                chars.next();
+            }
+
+            if chars.peek().is_none() {
+                break;
+            }
+
+            let mut key = String::new();
+
+            loop {
+                match chars.next() {
+                    Some('=') => break,
+                    Some(c) => key.push(c),
+                    None => return Err(format!("missing value of {}", key.trim())),
+                }
+            }
+
+            let key = key.trim();
+            let mut value = String::new();
+
+            if chars.peek() == Some(&'"') {
+                chars.next();
+
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => value.extend(chars.next()),
+                        Some(c) => value.push(c),
+                        None => return Err(format!("unterminated value of {}", key)),
+                    }
+                }
+            } else {
+                while let Some(c) = chars.peek().cloned().filter(|c| !c.is_whitespace()) {
+                    value.push(c);
+                    chars.next();
+                }
+            }
+
+            criteria.set(key, &value)?;
+        }
+
+        Ok(criteria)
+    }
+
+    /// Set a criterion given by its key in the bracketed form.
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let invalid = || format!("invalid value of {}: {}", key, value);
+
+        match key {
+            "id" => {
+                let window = match value.strip_prefix("0x") {
+                    Some(hex) => xproto::Window::from_str_radix(hex, 16),
+                    None => value.parse(),
+                };
+
+                self.window = Some(window.map_err(|_| invalid())?);
+            },
+            "class" => self.class = Some(Pattern::parse(value)?),
+            "instance" => self.instance = Some(Pattern::parse(value)?),
+            "title" => self.title = Some(Pattern::parse(value)?),
+            "window_type" =>
+                self.window_type = Some(WindowType::from_name(value).ok_or_else(invalid)?),
+            "transient" => self.transient = Some(value.parse().map_err(|_| invalid())?),
+            "tag" => self.tag = Some(config::TAGS
+                .iter()
+                .find(|t| t.to_string() == value)
+                .cloned()
+                .ok_or_else(invalid)?),
+            "urgent" => self.urgent = Some(value.parse().map_err(|_| invalid())?),
+            "" => return Err("missing criterion".to_owned()),
+            k => return Err(format!("unknown criterion: {}", k)),
+        }
+
+        Ok(())
+    }
+
+    /// Check whether the criteria only concern the properties of a client's window.
+    ///
+    /// Only such criteria can be checked before the client is managed.
+    pub fn only_properties(&self) -> bool {
+        self.tag.is_none() && self.urgent.is_none()
+    }
+
+    /// Check whether a window with the given properties meets the criteria concerning them.
+    ///
+    /// Criteria on the state of the client, such as its tags, are ignored.
+    pub fn matches_properties(&self, window: xproto::Window, properties: &ClientProperties)
+        -> bool
+    {
+        let class = properties.class.as_ref();
+
+        self.window.is_none_or(|w| w == window) &&
+            self.class.as_ref().is_none_or(|c| class.is_some_and(|p| c.matches(&p.class))) &&
+            self.instance
+                .as_ref()
+                .is_none_or(|i| class.is_some_and(|p| i.matches(&p.instance))) &&
+            self.title.as_ref().is_none_or(|t| t.matches(properties.title())) &&
+            self.window_type.is_none_or(|t| properties.has_type(t)) &&
+            self.transient.is_none_or(|t| properties.transient_for.is_some() == t)
+    }
+
+    /// Check whether a managed client meets the criteria.
+    pub fn matches(&self, client: &Client<xproto::Window>) -> bool {
+        self.matches_properties(*client.id(), client.properties()) &&
+            self.tag.as_ref().is_none_or(|t| client.tags().contains(t)) &&
+            self.urgent.is_none_or(|u| client.is_urgent() == u)
+    }
+}
+
+/// A search for a client by a part of its title or class, as typed into a menu.
+#[derive(Debug)]
+pub enum Search {
+    /// The title, class or instance has to contain the string, ignoring case.
+    Substring(String),
+    /// The title, class or instance has to match the regular expression.
+    Regex(Regex),
+}
+
+impl Search {
+    /// Parse a search query, which is a regular expression if prefixed with `~`.
+    pub fn parse(query: &str) -> Result<Search, String> {
+        match Pattern::parse(query)? {
+            Pattern::Exact(s) => Ok(Search::Substring(s.to_lowercase())),
+            Pattern::Regex(r) => Ok(Search::Regex(r)),
+        }
+    }
+
+    /// Rate how well a window with the given properties matches, `0` meaning not at all.
+    ///
+    /// Substrings rank highest when equal to a property, and higher when starting it than
+    /// when found elsewhere.
+    fn score(&self, properties: &ClientProperties) -> u8 {
+        let mut values = vec![properties.title()];
+
+        if let Some(ref class) = properties.class {
+            values.push(&class.class);
+            values.push(&class.instance);
+        }
+
+        values
+            .into_iter()
+            .map(|value| match self {
+                Search::Substring(s) => {
+                    let value = value.to_lowercase();
+
+                    if value == *s {
+                        3
+                    } else if value.starts_with(s.as_str()) {
+                        2
+                    } else if value.contains(s.as_str()) {
+                        1
+                    } else {
+                        0
+                    }
+                },
+                Search::Regex(r) => if r.is_match(value) { 1 } else { 0 },
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Find the client located in a tagset that matches best.
+    ///
+    /// Among clients matching equally well, the one focused most recently is chosen.
+    pub fn find<'a>(&self, hierarchy: &'a ClientHierarchy<xproto::Window>)
+        -> Option<&'a xproto::Window>
+    {
+        let hidden = hierarchy.hidden_scratchpad_clients();
+        let mut best = None;
+
+        for window in hierarchy.focus_history() {
+            if hidden.contains(&window) {
+                continue;
+            }
+
+            let score = hierarchy.get_client(window).map_or(0, |c| self.score(c.properties()));
+
+            if score > best.map_or(0, |(s, _)| s) {
+                best = Some((score, window));
+            }
+        }
+
+        best.map(|(_, window)| window)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeSet, HashSet};
+
+    use layout::{Geometry, Manual};
+    use properties::WmClass;
+    use tree::{Placement, Screen, SplitRatio, SplitType, TagSet, TagTree};
+
+    use super::*;
+
+    fn properties() -> ClientProperties {
+        let mut properties = ClientProperties::default();
+
+        properties.class =
+            Some(WmClass { instance: "Navigator".to_owned(), class: "Firefox".to_owned() });
+        properties.wm_name = Some("Google Meet - call".to_owned());
+
+        properties
+    }
+
+    fn client(tag: i8) -> Client<xproto::Window> {
+        let mut tags = HashSet::new();
+        tags.insert(Tag::Work(tag));
+
+        Client::new(7, properties(), tags)
+    }
+
+    /// Construct a hierarchy with a single screen showing tag 1, and clients on it with the
+    /// given ids, classes and titles, added in order.
+    fn hierarchy(clients: &[(xproto::Window, &str, &str)]) -> ClientHierarchy<xproto::Window> {
+        let mut hierarchy = ClientHierarchy::new();
+        let mut tags = BTreeSet::new();
+        tags.insert(Tag::Work(1));
+
+        let tree = TagTree::new(SplitType::Vertical(SplitRatio::default()));
+        let layout = Box::new(Manual { title_height: 0 });
+        let tagset = hierarchy.add_tagset(TagSet::new(tags, tree, layout));
+
+        hierarchy.add_screen(Screen::new(Geometry::new(0, 0, 1200, 800), tagset));
+
+        for &(id, class, title) in clients {
+            let mut properties = ClientProperties::default();
+            properties.class =
+                Some(WmClass { instance: class.to_lowercase(), class: class.to_owned() });
+            properties.wm_name = Some(title.to_owned());
+
+            let mut tags = HashSet::new();
+            tags.insert(Tag::Work(1));
+
+            hierarchy.add_client(Client::new(id, properties, tags), &Placement::default());
+        }
+
+        hierarchy
+    }
+
+    #[test]
+    fn criteria_parse() {
+        assert!(Criteria::parse("[]").is_ok());
+        assert!(Criteria::parse(r#"[title="a \" b"]"#).is_ok());
+        assert!(Criteria::parse("[bogus=1]").is_err());
+        assert!(Criteria::parse("[class]").is_err());
+        assert!(Criteria::parse("[tag=99]").is_err());
+        assert!(Criteria::parse(r#"[title="~("]"#).is_err());
+        assert!(Criteria::parse("class=x").is_err());
+
+        assert!(Criteria::parse("[class=Firefox]").unwrap().only_properties());
+        assert!(!Criteria::parse("[tag=3]").unwrap().only_properties());
+    }
+
+    #[test]
+    fn criteria_match_properties() {
+        let matches = |criteria| {
+            Criteria::parse(criteria).unwrap().matches_properties(7, &properties())
+        };
+
+        assert!(matches("[class=Firefox id=0x7]"));
+        assert!(matches("[instance=Navigator id=7]"));
+        assert!(matches(r#"[title="Google Meet - call"]"#));
+        assert!(matches(r#"[title="~.*Meet.*"]"#));
+        assert!(!matches("[class=Firefox id=8]"));
+        assert!(!matches("[class=Fire]"));
+        assert!(!matches(r#"[title="~^Meet"]"#));
+    }
+
+    #[test]
+    fn criteria_match_clients() {
+        let criteria = Criteria::parse(r#"[class="Firefox" title="~.*Meet.*" tag=3]"#).unwrap();
+
+        assert!(criteria.matches(&client(3)));
+        assert!(!criteria.matches(&client(2)));
+        assert!(Criteria::parse("[urgent=false]").unwrap().matches(&client(2)));
+        assert!(!Criteria::parse("[urgent=true]").unwrap().matches(&client(2)));
+    }
+
+    #[test]
+    fn search_ranks_exact_over_prefix_over_substring() {
+        let hierarchy = hierarchy(&[
+            (1, "St", "notes - vim"),
+            (2, "St", "vim notes"),
+            (3, "Vim", "notes"),
+        ]);
+
+        assert_eq!(Search::parse("VIM").unwrap().find(&hierarchy), Some(&3));
+        assert_eq!(Search::parse("vim n").unwrap().find(&hierarchy), Some(&2));
+        assert_eq!(Search::parse("- vim").unwrap().find(&hierarchy), Some(&1));
+        assert_eq!(Search::parse("emacs").unwrap().find(&hierarchy), None);
+    }
+
+    #[test]
+    fn search_prefers_recently_focused_clients() {
+        let mut hierarchy = hierarchy(&[
+            (1, "St", "vim notes"),
+            (2, "St", "vim code"),
+            (3, "Firefox", "Vim tips"),
+        ]);
+        let search = Search::parse("vim").unwrap();
+
+        assert_eq!(search.find(&hierarchy), Some(&1));
+
+        hierarchy.record_focus(&3);
+        hierarchy.record_focus(&2);
+
+        assert_eq!(search.find(&hierarchy), Some(&2));
+        assert_eq!(Search::parse("~tips$").unwrap().find(&hierarchy), Some(&3));
+        assert!(Search::parse("~(").is_err());
+    }
+
+    #[test]
+    fn search_skips_hidden_scratchpad_clients() {
+        let mut hierarchy = hierarchy(&[(1, "St", "vim"), (2, "St", "vim notes")]);
+        hierarchy.add_scratchpad("term", vec!["St".to_owned()]);
+
+        assert!(hierarchy.move_to_scratchpad("term", 1));
+        assert_eq!(Search::parse("vim").unwrap().find(&hierarchy), Some(&2));
+    }
+}
@@ -0,0 +1,96 @@
+use std::io::Error as IoError;
+
+use getopts;
+use regex;
+use toml;
+use xcb::base;
+
+/// An error occured when interacting with X.
+#[derive(Debug)]
+pub enum XError {
+    /// Could not connect to the X server.
+    CouldNotConnect(base::ConnError),
+    /// The screen being used didn't exist.
+    CouldNotAcquireScreen,
+    /// The configured font could not be opened.
+    CouldNotOpenFont(&'static str),
+    /// An atom could not be interned.
+    CouldNotInternAtom(&'static str),
+    /// Another window manager is already running.
+    OtherWmRunning,
+    /// A request to the X server failed.
+    RequestFailed(base::GenericError),
+    /// An I/O error occured.
+    IOError,
+}
+
+impl XError {
+    pub fn wrap(self) -> WmError {
+        WmError::X(self)
+    }
+
+    fn handle(self) -> ! {
+        use err::XError::*;
+
+        match self {
+            CouldNotConnect(e) => error!("Could not connect to X server: {}", e),
+            CouldNotAcquireScreen => error!("Screen is invalid"),
+            CouldNotOpenFont(f) => error!("Could not open font: {}", f),
+            CouldNotInternAtom(a) => error!("Could not intern atom: {}", a),
+            OtherWmRunning => error!("Another window manager is already running"),
+            RequestFailed(e) => error!("Generic X error: {}", e),
+            IOError => error!("An I/O error occured when communicating with the X server"),
+        }
+
+        ::std::process::exit(1);
+    }
+}
+
+/// An error occured during operation.
+#[derive(Debug)]
+pub enum WmError {
+    /// Error during command line parsing.
+    CouldNotParseOptions(getopts::Fail),
+    /// An I/O error occured.
+    IOError(IoError),
+    /// The TOML content of the config file is invalid.
+    TomlError(toml::de::Error),
+    /// The TOML file does not contain a toplevel table.
+    TomlNotTable,
+    /// A config key holds a value of the wrong type.
+    KeyTypeMismatch(String),
+    /// A config key holds a value that is not allowed.
+    InvalidValue(String, String),
+    /// A config key is not known.
+    UnknownKey(String),
+    /// A regular expression in the config is invalid.
+    InvalidRegex(String, regex::Error),
+    /// A config key holds criteria that are invalid or not allowed there.
+    InvalidCriteria(String, String),
+    /// An error encountered when interacting with X.
+    X(XError),
+}
+
+impl WmError {
+    pub fn handle(self) -> ! {
+        use err::WmError::*;
+
+        match self {
+            CouldNotParseOptions(f) => error!("{}", f),
+            IOError(i) => error!("I/O error occured: {}", i),
+            TomlError(t) => error!("TOML parsing of config failed: {}", t),
+            TomlNotTable => error!("config is not a table at the top level"),
+            KeyTypeMismatch(k) => error!("key {} has incorrect type", k),
+            InvalidValue(k, v) => error!("key {} has invalid value: {}", k, v),
+            UnknownKey(k) => error!("unknown config key: {}", k),
+            InvalidRegex(r, e) => error!("invalid regular expression {}: {}", r, e),
+            InvalidCriteria(k, e) => error!("key {} has invalid criteria: {}", k, e),
+            X(e) => e.handle(),
+        }
+
+        ::std::process::exit(1);
+    }
+}
+
+/// A result returned by fallible operations of the window manager.
+pub type WmResult<T> = Result<T, WmError>;
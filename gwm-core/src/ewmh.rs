@@ -0,0 +1,286 @@
+use xcb::base::*;
+use xcb::xproto;
+
+use atoms::Atoms;
+use err::*;
+use properties::WindowState;
+
+/// The name announced to EWMH clients.
+const WM_NAME: &str = "gabelstaplerwm";
+
+/// The desktop denoting all desktops in `_NET_WM_DESKTOP`.
+pub const ALL_DESKTOPS: u32 = 0xffff_ffff;
+
+/// An action requested by a `_NET_WM_STATE` client message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StateAction {
+    /// Remove the state.
+    Remove,
+    /// Add the state.
+    Add,
+    /// Add the state if it is missing, remove it otherwise.
+    Toggle,
+}
+
+impl StateAction {
+    fn from_u32(action: u32) -> Option<StateAction> {
+        match action {
+            0 => Some(StateAction::Remove),
+            1 => Some(StateAction::Add),
+            2 => Some(StateAction::Toggle),
+            _ => None,
+        }
+    }
+
+    /// Apply the action to a set of states.
+    ///
+    /// Returns `true` if the set has changed.
+    pub fn apply(self, states: &mut Vec<WindowState>, state: WindowState) -> bool {
+        let present = states.contains(&state);
+
+        match self {
+            StateAction::Add if !present => states.push(state),
+            StateAction::Remove | StateAction::Toggle if present =>
+                states.retain(|s| *s != state),
+            StateAction::Toggle => states.push(state),
+            _ => return false,
+        }
+
+        true
+    }
+}
+
+/// A request to change the geometry of a window, as sent in `_NET_MOVERESIZE_WINDOW`.
+///
+/// Only the fields present in the request are set.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MoveResize {
+    pub x: Option<i32>,
+    pub y: Option<i32>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+/// A client message understood by the window manager.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClientMessage {
+    /// A request to activate a window.
+    ActiveWindow(xproto::Window),
+    /// A request to close a window.
+    CloseWindow(xproto::Window),
+    /// A request to change up to two states of a window.
+    WmState(xproto::Window, StateAction, WindowState, Option<WindowState>),
+    /// A request to move and/or resize a window.
+    MoveResizeWindow(xproto::Window, MoveResize),
+    /// A request to switch to a desktop.
+    CurrentDesktop(u32),
+    /// A request to move a window to a desktop.
+    WmDesktop(xproto::Window, u32),
+}
+
+impl ClientMessage {
+    /// Decode a client message event.
+    ///
+    /// Returns `None` for messages the window manager doesn't handle.
+    pub fn decode(atoms: &Atoms, event: &xproto::ClientMessageEvent) -> Option<ClientMessage> {
+        if event.format() != 32 {
+            return None;
+        }
+
+        let window = event.window();
+        let data = event.data().data32();
+        let type_ = event.type_();
+
+        if type_ == atoms.net_active_window {
+            Some(ClientMessage::ActiveWindow(window))
+        } else if type_ == atoms.net_close_window {
+            Some(ClientMessage::CloseWindow(window))
+        } else if type_ == atoms.net_wm_state {
+            let action = StateAction::from_u32(data[0])?;
+            let first = WindowState::from_atom(atoms, data[1]);
+            let second = if data[2] != NONE {
+                Some(WindowState::from_atom(atoms, data[2]))
+            } else {
+                None
+            };
+
+            Some(ClientMessage::WmState(window, action, first, second))
+        } else if type_ == atoms.net_moveresize_window {
+            let flag = |bit: u32| data[0] & (1 << bit) != 0;
+
+            Some(ClientMessage::MoveResizeWindow(window, MoveResize {
+                x: if flag(8) { Some(data[1] as i32) } else { None },
+                y: if flag(9) { Some(data[2] as i32) } else { None },
+                width: if flag(10) { Some(data[3]) } else { None },
+                height: if flag(11) { Some(data[4]) } else { None },
+            }))
+        } else if type_ == atoms.net_current_desktop {
+            Some(ClientMessage::CurrentDesktop(data[0]))
+        } else if type_ == atoms.net_wm_desktop {
+            Some(ClientMessage::WmDesktop(window, data[0]))
+        } else {
+            None
+        }
+    }
+}
+
+/// The EWMH properties published by the window manager on the root window.
+pub struct Ewmh<'a> {
+    /// X connection used to communicate.
+    con: &'a Connection,
+    /// Root window.
+    root: xproto::Window,
+    /// The child window used to signal a compliant window manager being active.
+    check: xproto::Window,
+}
+
+impl<'a> Ewmh<'a> {
+    /// Create the supporting window manager check window and announce the supported hints.
+    ///
+    /// If `desktops` is set, the hints related to desktops are supported as well.
+    pub fn new(con: &'a Connection,
+               root: xproto::Window,
+               atoms: &Atoms,
+               desktops: bool) -> WmResult<Self>
+    {
+        let check = con.generate_id();
+
+        xproto::create_window_checked(con,
+                                      COPY_FROM_PARENT as u8,
+                                      check,
+                                      root,
+                                      -1, -1, 1, 1, 0,
+                                      xproto::WINDOW_CLASS_INPUT_ONLY as u16,
+                                      COPY_FROM_PARENT,
+                                      &[(xproto::CW_OVERRIDE_REDIRECT, 1)])
+            .request_check()
+            .map_err(|e| XError::RequestFailed(e).wrap())?;
+
+        let ewmh = Ewmh { con, root, check };
+
+        for window in &[root, check] {
+            ewmh.set_windows(*window, atoms.net_supporting_wm_check, &[check]);
+        }
+
+        xproto::change_property(con,
+                                xproto::PROP_MODE_REPLACE as u8,
+                                check,
+                                atoms.net_wm_name,
+                                atoms.utf8_string,
+                                8,
+                                WM_NAME.as_bytes());
+
+        let mut supported = vec![
+            atoms.net_supported,
+            atoms.net_supporting_wm_check,
+            atoms.net_client_list,
+            atoms.net_client_list_stacking,
+            atoms.net_active_window,
+            atoms.net_close_window,
+            atoms.net_moveresize_window,
+            atoms.net_wm_name,
+            atoms.net_wm_state,
+        ];
+
+        if desktops {
+            supported.extend_from_slice(&[
+                atoms.net_number_of_desktops,
+                atoms.net_desktop_names,
+                atoms.net_current_desktop,
+                atoms.net_wm_desktop,
+            ]);
+        }
+
+        xproto::change_property(con,
+                                xproto::PROP_MODE_REPLACE as u8,
+                                root,
+                                atoms.net_supported,
+                                xproto::ATOM_ATOM,
+                                32,
+                                &supported);
+
+        Ok(ewmh)
+    }
+
+    /// Publish the managed clients, in the order they have been mapped and bottom to top.
+    pub fn set_client_list(&self,
+                           atoms: &Atoms,
+                           clients: &[xproto::Window],
+                           stacking: &[xproto::Window]) {
+        self.set_windows(self.root, atoms.net_client_list, clients);
+        self.set_windows(self.root, atoms.net_client_list_stacking, stacking);
+    }
+
+    /// Publish the focused client.
+    pub fn set_active_window(&self, atoms: &Atoms, window: Option<xproto::Window>) {
+        self.set_windows(self.root, atoms.net_active_window, &[window.unwrap_or(NONE)]);
+    }
+
+    /// Publish the states of a client on the client window.
+    pub fn set_wm_state(&self, atoms: &Atoms, window: xproto::Window, states: &[WindowState]) {
+        let states: Vec<_> = states.iter().map(|s| s.to_atom(atoms)).collect();
+
+        xproto::change_property(self.con,
+                                xproto::PROP_MODE_REPLACE as u8,
+                                window,
+                                atoms.net_wm_state,
+                                xproto::ATOM_ATOM,
+                                32,
+                                &states);
+    }
+
+    /// Publish the names of all desktops.
+    pub fn set_desktops(&self, atoms: &Atoms, names: &[String]) {
+        let mut data = Vec::new();
+
+        for name in names {
+            data.extend_from_slice(name.as_bytes());
+            data.push(0);
+        }
+
+        self.set_cardinal(self.root, atoms.net_number_of_desktops, names.len() as u32);
+        xproto::change_property(self.con,
+                                xproto::PROP_MODE_REPLACE as u8,
+                                self.root,
+                                atoms.net_desktop_names,
+                                atoms.utf8_string,
+                                8,
+                                &data);
+    }
+
+    /// Publish the desktop currently shown.
+    pub fn set_current_desktop(&self, atoms: &Atoms, desktop: u32) {
+        self.set_cardinal(self.root, atoms.net_current_desktop, desktop);
+    }
+
+    /// Publish the desktop of a client, `ALL_DESKTOPS` denoting all of them.
+    pub fn set_wm_desktop(&self, atoms: &Atoms, window: xproto::Window, desktop: u32) {
+        self.set_cardinal(window, atoms.net_wm_desktop, desktop);
+    }
+
+    fn set_cardinal(&self, window: xproto::Window, property: xproto::Atom, value: u32) {
+        xproto::change_property(self.con,
+                                xproto::PROP_MODE_REPLACE as u8,
+                                window,
+                                property,
+                                xproto::ATOM_CARDINAL,
+                                32,
+                                &[value]);
+    }
+
+    fn set_windows(&self, window: xproto::Window, property: xproto::Atom, windows: &[u32]) {
+        xproto::change_property(self.con,
+                                xproto::PROP_MODE_REPLACE as u8,
+                                window,
+                                property,
+                                xproto::ATOM_WINDOW,
+                                32,
+                                windows);
+    }
+}
+
+impl<'a> Drop for Ewmh<'a> {
+    fn drop(&mut self) {
+        xproto::destroy_window(self.con, self.check);
+    }
+}
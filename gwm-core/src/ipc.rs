@@ -0,0 +1,286 @@
+//! The server side of the control socket.
+//!
+//! The protocol itself is defined in the `gwm_ipc` crate.
+
+use std::fs;
+use std::io::{self, BufRead, Read, Write};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+
+use gwm_ipc::{decode, encode, socket_path, ErrorKind, Event, EventClass, Request, Response};
+
+/// The amount of output a client may leave unread before it is dropped, in bytes.
+const MAX_PENDING: usize = 1 << 20;
+
+/// A connection to a client of the control socket.
+struct IpcClient {
+    /// The socket the client is connected on.
+    stream: UnixStream,
+    /// Data received that doesn't form a complete line yet.
+    buffer: Vec<u8>,
+    /// Data queued for the client that couldn't be sent yet.
+    outgoing: Vec<u8>,
+    /// Whether the client has stopped sending, in which case it is kept until all output
+    /// queued for it has been sent.
+    closed: bool,
+    /// The classes of events the client has subscribed to.
+    subscriptions: Vec<EventClass>,
+}
+
+impl IpcClient {
+    /// Read all data available without blocking.
+    ///
+    /// Returns `false` if the connection has been closed.
+    fn receive(&mut self) -> bool {
+        let mut chunk = [0; 4096];
+
+        if self.closed {
+            return false;
+        }
+
+        loop {
+            match self.stream.read(&mut chunk) {
+                Ok(0) => {
+                    self.closed = true;
+                    return false;
+                },
+                Ok(n) => self.buffer.extend_from_slice(&chunk[..n]),
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return true,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(_) => return false,
+            }
+        }
+    }
+
+    /// Take the complete lines received so far.
+    ///
+    /// Lines that aren't valid UTF-8 are returned as errors, so that they can be answered.
+    fn lines(&mut self) -> Vec<io::Result<String>> {
+        let end = match self.buffer.iter().rposition(|b| *b == b'\n') {
+            Some(i) => i + 1,
+            None => return Vec::new(),
+        };
+
+        let complete: Vec<_> = self.buffer.drain(..end).collect();
+
+        complete
+            .lines()
+            .filter(|l| l.as_ref().map_or(true, |l| !l.trim().is_empty()))
+            .collect()
+    }
+
+    /// Queue an encoded line for the client, and send as much output as possible.
+    ///
+    /// Returns `false` if the connection has failed, or the client has left too much output
+    /// unread.
+    fn send(&mut self, line: &str) -> bool {
+        if self.outgoing.len() + line.len() > MAX_PENDING {
+            warn!("control client doesn't read its messages, dropping it");
+            return false;
+        }
+
+        self.outgoing.extend_from_slice(line.as_bytes());
+        self.flush()
+    }
+
+    /// Send as much of the queued output as possible without blocking.
+    ///
+    /// Returns `false` if the connection has failed.
+    fn flush(&mut self) -> bool {
+        while !self.outgoing.is_empty() {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => return false,
+                Ok(n) => {
+                    self.outgoing.drain(..n);
+                },
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return true,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(_) => return false,
+            }
+        }
+
+        true
+    }
+}
+
+/// The server side of the control socket.
+pub struct IpcServer {
+    /// The socket listening for new connections.
+    listener: UnixListener,
+    /// The path the socket is bound to, removed when the server is dropped.
+    path: PathBuf,
+    /// The clients currently connected.
+    clients: Vec<IpcClient>,
+}
+
+impl IpcServer {
+    /// Bind the control socket.
+    ///
+    /// A stale socket left behind by a previous instance is replaced, but binding fails if
+    /// another instance is still listening on it.
+    pub fn bind() -> io::Result<IpcServer> {
+        let path = socket_path().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "XDG_RUNTIME_DIR is not set")
+        })?;
+
+        if path.exists() {
+            if UnixStream::connect(&path).is_ok() {
+                return Err(io::Error::new(io::ErrorKind::AddrInUse,
+                                          "control socket is in use"));
+            }
+
+            fs::remove_file(&path)?;
+        }
+
+        let listener = UnixListener::bind(&path)?;
+        listener.set_nonblocking(true)?;
+
+        info!("listening on {}", path.display());
+
+        Ok(IpcServer {
+            listener,
+            path,
+            clients: Vec::new(),
+        })
+    }
+
+    /// Get the file descriptors to wait on for new connections and requests.
+    pub fn fds(&self) -> Vec<RawFd> {
+        Some(self.listener.as_raw_fd())
+            .into_iter()
+            .chain(self.clients.iter().filter(|c| !c.closed).map(|c| c.stream.as_raw_fd()))
+            .collect()
+    }
+
+    /// Get the file descriptors to wait on until they become writable, as output queued for
+    /// their clients couldn't be sent yet.
+    pub fn pending_fds(&self) -> Vec<RawFd> {
+        self.clients
+            .iter()
+            .filter(|c| !c.outgoing.is_empty())
+            .map(|c| c.stream.as_raw_fd())
+            .collect()
+    }
+
+    /// Accept new connections, send queued output, and answer all complete requests received
+    /// using a handler.
+    ///
+    /// Requests that can't be parsed are answered without calling the handler. Clients that
+    /// have disconnected, or can't be written to, are dropped.
+    pub fn process<F>(&mut self, mut handler: F)
+        where F: FnMut(Request) -> Response
+    {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    if stream.set_nonblocking(true).is_ok() {
+                        debug!("control client connected");
+                        self.clients.push(IpcClient {
+                            stream,
+                            buffer: Vec::new(),
+                            outgoing: Vec::new(),
+                            closed: false,
+                            subscriptions: Vec::new(),
+                        });
+                    }
+                },
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    warn!("could not accept control client: {}", e);
+                    break;
+                },
+            }
+        }
+
+        let mut i = 0;
+
+        while i < self.clients.len() {
+            if Self::serve(&mut self.clients[i], &mut handler) {
+                i += 1;
+            } else {
+                debug!("control client disconnected");
+                self.clients.swap_remove(i);
+            }
+        }
+    }
+
+    /// Answer the requests received from a client.
+    ///
+    /// Returns `false` if the client is to be dropped.
+    fn serve<F>(client: &mut IpcClient, handler: &mut F) -> bool
+        where F: FnMut(Request) -> Response
+    {
+        if !client.flush() {
+            return false;
+        }
+
+        let open = client.receive();
+
+        for line in client.lines() {
+            let request = match line {
+                Ok(line) => decode(&line).map_err(|e| e.to_string()),
+                Err(_) => Err("request is not valid UTF-8".to_owned()),
+            };
+
+            let response = match request {
+                // subscriptions concern the connection only
+                Ok(Request::Subscribe { events }) => {
+                    client.subscriptions = events;
+                    Response::ok(None)
+                },
+                Ok(request) => {
+                    debug!("control request: {:?}", request);
+                    handler(request)
+                },
+                Err(message) => Response::error(ErrorKind::InvalidRequest, message),
+            };
+
+            if !client.send(&encode(&response)) {
+                return false;
+            }
+        }
+
+        // clients that stopped sending still receive the responses queued for them
+        open || !client.outgoing.is_empty()
+    }
+
+    /// Check whether any client has subscribed to events.
+    pub fn has_subscribers(&self) -> bool {
+        self.clients.iter().any(|c| !c.subscriptions.is_empty())
+    }
+
+    /// Send events to the clients subscribed to them, without waiting for slow clients.
+    ///
+    /// Clients that can't be written to, or leave too many events unread, are dropped.
+    pub fn broadcast(&mut self, events: &[Event]) {
+        let encoded: Vec<_> = events
+            .iter()
+            .map(|e| (e.class(), encode(e)))
+            .collect();
+
+        let mut i = 0;
+
+        while i < self.clients.len() {
+            let client = &mut self.clients[i];
+            let sent = encoded
+                .iter()
+                .all(|&(class, ref line)| {
+                    !client.subscriptions.contains(&class) || client.send(line)
+                });
+
+            if sent {
+                i += 1;
+            } else {
+                self.clients.swap_remove(i);
+            }
+        }
+    }
+}
+
+impl Drop for IpcServer {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
@@ -10,7 +10,7 @@ use tree::{ArenaContainerId, ContainerId, Container, SplitRatio, SplitType, TagT
 /// A rectangle somewhere on screen.
 ///
 /// Could represent a client's geometry, a screen, or something else.
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Geometry {
     /// The x coordinate of the upper left corner of the rectangle.
     x: u32,
@@ -23,6 +23,32 @@ pub struct Geometry {
 }
 
 impl Geometry {
+    /// Construct a geometry from the position of its upper left corner and its dimensions.
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Geometry {
+        Geometry {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn x(&self) -> u32 {
+        self.x
+    }
+
+    pub fn y(&self) -> u32 {
+        self.y
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
     /// Split the given geometry horizontally in two.
     ///
     /// Return a pair of subgeometries (left first) computed in the split.
@@ -71,36 +97,75 @@ impl Geometry {
         (top, bot)
     }
 
-    /// Split the given geometry horizontally in equal subgeometries.
+    /// Split the given geometry horizontally in subgeometries sized by the given weights.
     ///
-    /// Returns the leftmost subgeometry, and an x-offset for each next geometry.
-    pub fn split_horizontal_eq(&self, n: usize) -> (Geometry, u32) {
-        let width_prime = self.width / n as u32;
+    /// Returns the subgeometries from left to right.
+    pub fn split_horizontal_weighted(&self, weights: &[u32]) -> Vec<Geometry> {
+        Self::weighted_offsets(self.width, weights)
+            .map(|(x, width)| Geometry {
+                x: self.x + x,
+                y: self.y,
+                width,
+                height: self.height,
+            })
+            .collect()
+    }
 
-        let left = Geometry {
-            x: self.x,
-            y: self.y,
-            width: width_prime,
-            height: self.height,
-        };
+    /// Split the given geometry vertically in subgeometries sized by the given weights.
+    ///
+    /// Returns the subgeometries from top to bottom.
+    pub fn split_vertical_weighted(&self, weights: &[u32]) -> Vec<Geometry> {
+        Self::weighted_offsets(self.height, weights)
+            .map(|(y, height)| Geometry {
+                x: self.x,
+                y: self.y + y,
+                width: self.width,
+                height,
+            })
+            .collect()
+    }
 
-        (left, width_prime)
+    /// Divide a length into consecutive parts sized by the given weights.
+    ///
+    /// Yields the offset and length of each part.
+    fn weighted_offsets<'a>(length: u32, weights: &'a [u32])
+        -> impl Iterator<Item=(u32, u32)> + 'a
+    {
+        let total: u64 = weights.iter().map(|w| u64::from(*w)).sum();
+        let mut sum = 0;
+
+        weights.iter().map(move |w| {
+            let start = u64::from(length) * sum / total;
+            sum += u64::from(*w);
+            let end = u64::from(length) * sum / total;
+
+            (start as u32, (end - start) as u32)
+        })
     }
 
-    /// Split the given geometry vertically in equal subgeometries.
+    /// Cut a strip of the given height off the top of the given geometry.
     ///
-    /// Returns the topmost subgeometry, and an y-offset for each next geometry.
-    pub fn split_vertical_eq(&self, n: usize) -> (Geometry, u32) {
-        let height_prime = self.height / n as u32;
+    /// Returns the strip and the remaining geometry below it.
+    pub fn split_top(&self, height: u32) -> (Geometry, Geometry) {
+        use std::cmp::min;
+
+        let height = min(height, self.height);
 
         let top = Geometry {
             x: self.x,
             y: self.y,
             width: self.width,
-            height: height_prime,
+            height,
+        };
+
+        let rest = Geometry {
+            x: self.x,
+            y: self.y + height,
+            width: self.width,
+            height: self.height - height,
         };
 
-        (top, height_prime)
+        (top, rest)
     }
 
     /// Move the given geometry by the given offset in x direction.
@@ -131,17 +196,22 @@ impl Geometry {
         match split {
             SplitType::Horizontal(_) => self.x_offset(off),
             SplitType::Vertical(_) => self.y_offset(off),
-            SplitType::Tabbed => panic!("cannot offset geometry with tabbed split"),
+            SplitType::Tabbed | SplitType::Stacked =>
+                panic!("cannot offset geometry with tabbed or stacked split"),
         }
     }
 
+    /// Center the geometry on a reference geometry.
+    ///
+    /// Geometries larger than the reference are aligned with its upper left corner instead.
     pub fn center(&mut self, reference: &Geometry) {
-        self.x = reference.x + (reference.width / 2) - (self.width / 2);
-        self.y = reference.y + (reference.height / 2) - (self.height / 2);
+        self.x = reference.x + (reference.width / 2).saturating_sub(self.width / 2);
+        self.y = reference.y + (reference.height / 2).saturating_sub(self.height / 2);
     }
 }
 
 /// Geometrical direction (in a tag tree).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Direction {
     /// Geometric left (towards lower x-coordinates).
     Left,
@@ -166,14 +236,24 @@ pub enum Direction {
 }
 
 /// A modification message sent to a layout.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum LayoutMessage {
     ParamAbs { id: usize, value: usize },
-    ParamAdd { id: usize, inc: usize },
+    ParamAdd { id: usize, inc: isize },
 }
 
-/// A map holding clients' geometries as constructed by a layout.
+/// A map holding the geometries of the containers shown, as constructed by a layout.
+///
+/// Besides the clients, split containers are included, so that clients can be made to cover
+/// the container holding them.
 pub type ClientSizes = HashMap<ContainerId, Geometry>;
 
+/// A map holding the title areas reserved by a layout for tabbed and stacked containers.
+///
+/// Each visible container showing a single child at a time can have an area reserved above
+/// its children, which is where the titles of all children are drawn.
+pub type TitleAreas = HashMap<ContainerId, Geometry>;
+
 /// A layout that can be used to render tag trees on a geometry.
 ///
 /// Any layout type needs to uphold certain invariants to avoid surprising behaviour for
@@ -183,13 +263,18 @@ pub type ClientSizes = HashMap<ContainerId, Geometry>;
 /// a container upon removal. All fields tracking focus and selection are not maintained by
 /// the layout.
 pub trait Layout<C> : Debug {
+    /// Get the name of the layout, as shown to control socket clients.
+    fn name(&self) -> &'static str;
+
     /// Compute geometries of the given tag tree on a given geometry.
     ///
     /// The tag tree can be assumed to be consistent with the layout. The layout can either
     /// ignore floating containers completely, or provide geometries for them that are then
     /// used in the actual rendering process. In either case, the floating windows and/or
     /// containers are then drawn at the provided or generated locations beginning at the root.
-    fn render(&self, &TagTree<C>, &Geometry, &mut ClientSizes);
+    /// Layouts that display titles of tabbed or stacked containers reserve space for them and
+    /// record it in the title areas.
+    fn render(&self, &TagTree<C>, &Geometry, &mut ClientSizes, &mut TitleAreas);
 
     /// Check whether the tag tree is consistent with the layout.
     fn check_tree(&self, &TagTree<C>) -> bool;
@@ -244,41 +329,85 @@ pub trait Layout<C> : Debug {
 /// This layout essentially mirrors i3's approach to window management. The tag tree's
 /// contents are rendered directly, and can be of arbitrary structure.
 #[derive(Debug)]
-pub struct Manual { }
+pub struct Manual {
+    /// The height of a single title row drawn above the children of tabbed and stacked
+    /// containers.
+    pub title_height: u32,
+}
 
 impl<C> Layout<C> for Manual {
-    fn render(&self, tagtree: &TagTree<C>, target: &Geometry, sizes: &mut ClientSizes) {
+    fn name(&self) -> &'static str {
+        "manual"
+    }
+
+    fn render(&self,
+              tagtree: &TagTree<C>,
+              target: &Geometry,
+              sizes: &mut ClientSizes,
+              titles: &mut TitleAreas) {
         fn handle_split<C>(tagtree: &TagTree<C>,
                            geo_cache: &mut HashMap<ContainerId, (Geometry, bool)>,
+                           titles: &mut TitleAreas,
+                           title_height: u32,
                            current_id: ContainerId,
                            split_type: SplitType,
                            last_focused: Option<ArenaContainerId>)
         {
             let num_children = tagtree.num_children(current_id);
-            let (mut geo, offset) = match split_type {
-                SplitType::Vertical(_) => {
-                    geo_cache[&current_id].0.split_vertical_eq(num_children)
-                },
-                SplitType::Horizontal(_) => {
-                    geo_cache[&current_id].0.split_horizontal_eq(num_children)
+
+            if num_children == 0 {
+                return;
+            }
+
+            let (geo, rendered) = geo_cache[&current_id];
+            let geos = match split_type {
+                SplitType::Vertical(_) | SplitType::Horizontal(_) => {
+                    let weights: Vec<_> =
+                        tagtree.children(current_id).map(|(_, c)| c.weight()).collect();
+
+                    if let SplitType::Vertical(_) = split_type {
+                        geo.split_vertical_weighted(&weights)
+                    } else {
+                        geo.split_horizontal_weighted(&weights)
+                    }
                 },
                 SplitType::Tabbed => {
-                    (geo_cache[&current_id].0, 0)
+                    // reserve a single title row for all children
+                    let (title_area, rest) = geo.split_top(title_height);
+
+                    if rendered {
+                        titles.insert(current_id, title_area);
+                    }
+
+                    vec![rest; num_children]
+                },
+                SplitType::Stacked => {
+                    // reserve a title row for each child
+                    let (title_area, rest) = geo.split_top(title_height * num_children as u32);
+
+                    if rendered {
+                        titles.insert(current_id, title_area);
+                    }
+
+                    vec![rest; num_children]
                 },
             };
 
-            // handle hidden containers (the ones invisible in tabbed splits)
-            let children_hidden =
-                split_type != SplitType::Tabbed && geo_cache[&current_id].1;
+            // handle hidden containers (the ones invisible in tabbed and stacked splits)
+            let children_rendered = !split_type.shows_single_child() && rendered;
 
-            for (child_id, child) in tagtree.children(current_id) {
-                geo_cache.insert(ContainerId::Index(child_id), (geo, children_hidden));
-                geo = geo.offset(&split_type, offset as i32);
+            for ((child_id, _), geo) in tagtree.children(current_id).zip(geos) {
+                geo_cache.insert(ContainerId::Index(child_id), (geo, children_rendered));
             }
 
-            if let Some(l) = last_focused {
-                geo_cache.get_mut(&ContainerId::Index(l)).unwrap().1 =
-                    geo_cache[&current_id].1;
+            // the child containing the last focused client is the one visible, falling back to
+            // the first child if no client has been focused yet
+            let visible = last_focused
+                .and_then(|l| tagtree.child_containing(current_id, l))
+                .or_else(|| tagtree.children(current_id).next().map(|(c, _)| c));
+
+            if let Some(v) = visible {
+                geo_cache.get_mut(&ContainerId::Index(v)).unwrap().1 = rendered;
             }
         }
 
@@ -290,6 +419,8 @@ impl<C> Layout<C> for Manual {
 
         handle_split(tagtree,
                      &mut geo_cache,
+                     titles,
+                     self.title_height,
                      ContainerId::Root,
                      tagtree.root.split_type,
                      tagtree.root.get_focused());
@@ -299,9 +430,16 @@ impl<C> Layout<C> for Manual {
         for (current_id, current) in tagtree.preorder(ContainerId::Root) {
             let current_id = ContainerId::Index(current_id);
 
-            // just move floating containers to the middle of the screen
+            // move floating containers to their requested geometry, or the middle of the
+            // screen
             if current.floating() {
-                geo_cache.get_mut(&current_id).unwrap().0.center(target);
+                let cached = &mut geo_cache.get_mut(&current_id).unwrap().0;
+
+                if let Some(geometry) = current.floating_geometry() {
+                    *cached = geometry;
+                } else {
+                    cached.center(target);
+                }
             }
 
             // since we are iterating over the preorder traversal of the tree, we can
@@ -309,8 +447,14 @@ impl<C> Layout<C> for Manual {
             // container.
             match current {
                 Container::Split(s) => {
+                    if geo_cache[&current_id].1 {
+                        sizes.insert(current_id, geo_cache[&current_id].0);
+                    }
+
                     handle_split(tagtree,
                                  &mut geo_cache,
+                                 titles,
+                                 self.title_height,
                                  current_id,
                                  s.split_type,
                                  s.get_last_focused());
@@ -345,6 +489,7 @@ impl<C> Layout<C> for Manual {
 
     fn delete_container(&self, tagtree: &mut TagTree<C>, container: ContainerId) -> bool {
         tagtree.delete_container(container);
+        tagtree.normalize();
 
         // TODO: cleverly detect if a redraw is necessary. essentially, this requires some
         // intrusive handling of `last_focused` updates on tabbed containers.
@@ -354,24 +499,117 @@ impl<C> Layout<C> for Manual {
     fn find_container(&self, tagtree: &TagTree<C>, container: ContainerId, dir: Direction)
         -> Option<ContainerId>
     {
-        // TODO
-        None
+        let id = match container {
+            ContainerId::Index(i) => i,
+            ContainerId::Root => return None,
+        };
+
+        match dir {
+            Direction::Left | Direction::Up | Direction::Right | Direction::Down => {
+                let backward = dir == Direction::Left || dir == Direction::Up;
+                let mut current = id;
+
+                // climb up until a split in the right orientation has a sibling in the
+                // direction requested
+                loop {
+                    let c = tagtree.get_container(current)?;
+                    let parent = c.get_parent()?;
+
+                    let oriented = matches!((tagtree.get_split_type(parent)?, dir),
+                        (SplitType::Horizontal(_), Direction::Left) |
+                        (SplitType::Horizontal(_), Direction::Right) |
+                        (SplitType::Tabbed, Direction::Left) |
+                        (SplitType::Tabbed, Direction::Right) |
+                        (SplitType::Vertical(_), Direction::Up) |
+                        (SplitType::Vertical(_), Direction::Down) |
+                        (SplitType::Stacked, Direction::Up) |
+                        (SplitType::Stacked, Direction::Down));
+
+                    let sibling =
+                        if backward { c.get_prev_sibling() } else { c.get_next_sibling() };
+
+                    match (oriented, sibling, parent) {
+                        (true, Some(s), _) => return Some(ContainerId::Index(s)),
+                        (_, _, ContainerId::Index(p)) => current = p,
+                        (_, _, ContainerId::Root) => return None,
+                    }
+                }
+            },
+            Direction::SiblingCycleForward | Direction::SiblingCycleBackward => {
+                let c = tagtree.get_container(id)?;
+                let siblings = tagtree.children(c.get_parent()?);
+
+                let sibling = if dir == Direction::SiblingCycleForward {
+                    c.get_next_sibling().or_else(|| siblings.map(|(s, _)| s).next())
+                } else {
+                    c.get_prev_sibling().or_else(|| siblings.map(|(s, _)| s).last())
+                };
+
+                sibling.filter(|s| *s != id).map(ContainerId::Index)
+            },
+            _ => {
+                // clients are ordered identically in pre-order and in-order traversals
+                let nodes: Vec<_> = tagtree.preorder(ContainerId::Root).collect();
+                let pos = nodes.iter().position(|&(n, _)| n == id)?;
+                let end = pos + 1 + tagtree.preorder(container).count();
+
+                let forward =
+                    dir == Direction::PreOrderForward || dir == Direction::InOrderForward;
+
+                let mut candidates: Vec<_> =
+                    nodes[end..].iter().chain(nodes[..pos].iter()).collect();
+
+                if !forward {
+                    candidates.reverse();
+                }
+
+                candidates
+                    .into_iter()
+                    .find(|&&(_, c)| c.get_children().is_none())
+                    .map(|&(n, _)| ContainerId::Index(n))
+            },
+        }
     }
 
     fn swap_containers(&self,
                        tagtree: &mut TagTree<C>,
                        a: ContainerId,
                        b: ContainerId) -> bool {
-        // TODO
-        false
+        match (a, b) {
+            (ContainerId::Index(a), ContainerId::Index(b)) => {
+                let swapped = tagtree.swap_containers(a, b);
+                tagtree.normalize();
+
+                swapped
+            },
+            _ => false,
+        }
     }
 
     fn move_container(&self,
                       tagtree: &mut TagTree<C>,
                       cursor: ContainerId,
                       target: ContainerId) -> bool {
-        // TODO
-        false
+        let (cursor, target) = match (cursor, target) {
+            (ContainerId::Index(c), ContainerId::Index(t)) => (c, t),
+            _ => return false,
+        };
+
+        // the container moved passes the cursor in the direction it is located in
+        let before = tagtree
+            .preorder(ContainerId::Root)
+            .map(|(id, _)| id)
+            .find(|id| *id == cursor || *id == target) == Some(cursor);
+
+        let moved = if before {
+            tagtree.move_subtree_before(cursor, target)
+        } else {
+            tagtree.move_subtree_after(cursor, target)
+        };
+
+        tagtree.normalize();
+
+        moved
     }
 
     fn process_msg(&mut self, _: LayoutMessage) -> bool { false }
@@ -1,6 +1,23 @@
 #![feature(type_alias_enum_variants)]
 extern crate generational_arena;
+extern crate getopts;
+extern crate gwm_ipc;
+extern crate libc;
+#[macro_use]
+extern crate log;
+extern crate regex;
+extern crate toml;
+extern crate xcb;
 
+pub mod atoms;
 pub mod config;
+pub mod criteria;
+pub mod err;
+pub mod ewmh;
+pub mod ipc;
 pub mod layout;
+pub mod properties;
+pub mod rules;
+pub mod state;
+pub mod titles;
 pub mod tree;
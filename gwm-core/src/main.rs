@@ -0,0 +1,94 @@
+extern crate env_logger;
+extern crate getopts;
+extern crate gwm_core;
+#[macro_use]
+extern crate log;
+extern crate xcb;
+
+use getopts::Options;
+
+use std::env;
+use std::io;
+use std::path::Path;
+
+use xcb::base::*;
+
+use gwm_core::err::{WmError, WmResult, XError};
+use gwm_core::rules::Rules;
+use gwm_core::state::WmState;
+
+/// Initialize the logger.
+fn setup_logger() {
+    env_logger::init();
+    info!("initialized logger");
+
+    // clean environment for programs honoring `RUST_LOG`
+    env::remove_var("RUST_LOG");
+}
+
+/// Load the rules from the config file.
+///
+/// A missing config file is only an error if its path has been given explicitly.
+fn load_rules(path: &Path, explicit: bool) -> WmResult<Rules> {
+    match Rules::from_file(path) {
+        Err(WmError::IOError(ref e)) if !explicit && e.kind() == io::ErrorKind::NotFound => {
+            info!("no config file found at {}, using no rules", path.display());
+            Ok(Rules::default())
+        },
+        res => res,
+    }
+}
+
+/// Main routine.
+fn do_main(rules: Rules) -> WmResult<()> {
+    let (con, screen_num) = match Connection::connect(None) {
+        Ok(c) => c,
+        Err(e) => {
+            return Err(XError::CouldNotConnect(e).wrap());
+        },
+    };
+
+    let mut state = WmState::new(&con, screen_num, rules)?;
+    state.setup()?;
+    state.arrange();
+    state.run()
+}
+
+fn main() {
+    setup_logger();
+
+    let args: Vec<String> = env::args().collect();
+
+    // set up option parsing
+    let mut opts = Options::new();
+    opts.optopt("c", "config", "set config file name", "FILE");
+    opts.optflag("h", "help", "print this help menu");
+
+    // match on args and decide what to do
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(f) => WmError::CouldNotParseOptions(f).handle(),
+    };
+
+    if matches.opt_present("h") {
+        let brief = format!("Usage: {} [options]", &args[0]);
+        eprintln!("{}", opts.usage(&brief));
+        return;
+    }
+
+    let (config_path, explicit) = if let Some(p) = matches.opt_str("c") {
+        (p.into(), true)
+    } else if let Some(mut buf) = env::home_dir() {
+        buf.push(".gwmrc");
+        (buf, false)
+    } else {
+        error!("could not determine config file path, specify one with -c");
+        ::std::process::exit(1);
+    };
+
+    let res = load_rules(&config_path, explicit).and_then(do_main);
+
+    if let Err(e) = res {
+        e.handle();
+    }
+}
@@ -0,0 +1,643 @@
+use xcb::base::*;
+use xcb::xproto;
+
+use atoms::Atoms;
+use layout::Geometry;
+
+/// A property of a client window cached by the window manager.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Property {
+    /// `WM_CLASS`.
+    WmClass,
+    /// `WM_NAME`.
+    WmName,
+    /// `_NET_WM_NAME`.
+    NetWmName,
+    /// `WM_HINTS`.
+    WmHints,
+    /// `WM_NORMAL_HINTS`.
+    WmNormalHints,
+    /// `WM_PROTOCOLS`.
+    WmProtocols,
+    /// `WM_TRANSIENT_FOR`.
+    WmTransientFor,
+    /// `_NET_WM_WINDOW_TYPE`.
+    NetWmWindowType,
+    /// `_NET_WM_STATE`.
+    NetWmState,
+    /// `_NET_WM_PID`.
+    NetWmPid,
+    /// `_NET_WM_STRUT`.
+    NetWmStrut,
+    /// `_NET_WM_STRUT_PARTIAL`.
+    NetWmStrutPartial,
+}
+
+/// All cached properties, in the order they are fetched.
+const PROPERTIES: [Property; 12] = [
+    Property::WmClass,
+    Property::WmName,
+    Property::NetWmName,
+    Property::WmHints,
+    Property::WmNormalHints,
+    Property::WmProtocols,
+    Property::WmTransientFor,
+    Property::NetWmWindowType,
+    Property::NetWmState,
+    Property::NetWmPid,
+    Property::NetWmStrut,
+    Property::NetWmStrutPartial,
+];
+
+impl Property {
+    /// Get the atom naming the property.
+    pub fn atom(self, atoms: &Atoms) -> xproto::Atom {
+        match self {
+            Property::WmClass => xproto::ATOM_WM_CLASS,
+            Property::WmName => xproto::ATOM_WM_NAME,
+            Property::NetWmName => atoms.net_wm_name,
+            Property::WmHints => xproto::ATOM_WM_HINTS,
+            Property::WmNormalHints => xproto::ATOM_WM_NORMAL_HINTS,
+            Property::WmProtocols => atoms.wm_protocols,
+            Property::WmTransientFor => xproto::ATOM_WM_TRANSIENT_FOR,
+            Property::NetWmWindowType => atoms.net_wm_window_type,
+            Property::NetWmState => atoms.net_wm_state,
+            Property::NetWmPid => atoms.net_wm_pid,
+            Property::NetWmStrut => atoms.net_wm_strut,
+            Property::NetWmStrutPartial => atoms.net_wm_strut_partial,
+        }
+    }
+
+    /// Determine the property named by an atom, if it is cached.
+    pub fn from_atom(atoms: &Atoms, atom: xproto::Atom) -> Option<Property> {
+        PROPERTIES.iter().cloned().find(|p| p.atom(atoms) == atom)
+    }
+}
+
+/// The contents of a `WM_CLASS` property.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WmClass {
+    /// The instance name of the client.
+    pub instance: String,
+    /// The class name of the client.
+    pub class: String,
+}
+
+/// The contents of a `WM_HINTS` property relevant to window management.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WmHints {
+    /// Whether the client relies on the window manager to get input focus, if specified.
+    pub input: Option<bool>,
+    /// The state the client wants to be in when first mapped, if specified.
+    pub initial_state: Option<u32>,
+    /// Whether the client demands the user's attention.
+    pub urgent: bool,
+    /// The leader of the client's window group, if any.
+    pub window_group: Option<xproto::Window>,
+}
+
+/// The ICCCM input models, describing how a client is given the input focus.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputModel {
+    /// The client never expects the input focus.
+    NoInput,
+    /// The client gets the input focus set by the window manager.
+    Passive,
+    /// The client gets the input focus set, and is notified using `WM_TAKE_FOCUS`.
+    LocallyActive,
+    /// The client is only asked to take the input focus using `WM_TAKE_FOCUS`.
+    GloballyActive,
+}
+
+/// The contents of a `WM_NORMAL_HINTS` property relevant to window management.
+///
+/// Only the fields the client has actually specified are set.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SizeHints {
+    /// The minimum size of the client.
+    pub min: Option<(u32, u32)>,
+    /// The maximum size of the client.
+    pub max: Option<(u32, u32)>,
+    /// The increments the client wants to be resized in.
+    pub inc: Option<(u32, u32)>,
+    /// The minimum and maximum aspect ratios of the client, as fractions.
+    pub aspect: Option<((u32, u32), (u32, u32))>,
+    /// The base size of the client, used with resize increments.
+    pub base: Option<(u32, u32)>,
+}
+
+impl SizeHints {
+    /// Adjust a geometry to the size constraints, centering the result in it.
+    ///
+    /// The base size is subtracted before the aspect ratio and resize increments are applied,
+    /// as mandated by the ICCCM, and the result is clamped to the minimum and maximum size.
+    /// The geometry given is never exceeded.
+    pub fn constrain(&self, geometry: &Geometry) -> Geometry {
+        // per the ICCCM, the base and minimum size serve as defaults for one another
+        let (base_w, base_h) = self.base.or(self.min).unwrap_or((0, 0));
+        let (min_w, min_h) = self.min.or(self.base).unwrap_or((1, 1));
+
+        let mut width = geometry.width().saturating_sub(base_w);
+        let mut height = geometry.height().saturating_sub(base_h);
+
+        if let Some(((min_x, min_y), (max_x, max_y))) = self.aspect {
+            if min_x > 0 && min_y > 0 && max_x > 0 && max_y > 0 && width > 0 && height > 0 {
+                let ratio = f64::from(width) / f64::from(height);
+                let min_ratio = f64::from(min_x) / f64::from(min_y);
+                let max_ratio = f64::from(max_x) / f64::from(max_y);
+
+                if ratio > max_ratio {
+                    width = (f64::from(height) * max_ratio) as u32;
+                } else if ratio < min_ratio {
+                    height = (f64::from(width) / min_ratio) as u32;
+                }
+            }
+        }
+
+        if let Some((inc_w, inc_h)) = self.inc {
+            if inc_w > 0 {
+                width -= width % inc_w;
+            }
+
+            if inc_h > 0 {
+                height -= height % inc_h;
+            }
+        }
+
+        width = (width + base_w).max(min_w);
+        height = (height + base_h).max(min_h);
+
+        if let Some((max_w, max_h)) = self.max {
+            if max_w > 0 {
+                width = width.min(max_w);
+            }
+
+            if max_h > 0 {
+                height = height.min(max_h);
+            }
+        }
+
+        let mut result =
+            Geometry::new(0, 0, width.min(geometry.width()), height.min(geometry.height()));
+        result.center(geometry);
+
+        result
+    }
+}
+
+/// The space reserved by a dock at the edges of the X screen, as in `_NET_WM_STRUT_PARTIAL`.
+///
+/// The widths are measured from the respective edge of the root window. Each width applies
+/// to a range of coordinates along its edge, given as inclusive start and end.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Strut {
+    pub left: u32,
+    pub right: u32,
+    pub top: u32,
+    pub bottom: u32,
+    /// The range of y coordinates the left strut applies to.
+    pub left_range: (u32, u32),
+    /// The range of y coordinates the right strut applies to.
+    pub right_range: (u32, u32),
+    /// The range of x coordinates the top strut applies to.
+    pub top_range: (u32, u32),
+    /// The range of x coordinates the bottom strut applies to.
+    pub bottom_range: (u32, u32),
+}
+
+impl Strut {
+    /// Compute the part of a geometry not reserved by the strut.
+    ///
+    /// `root` is the geometry of the root window the strut widths are relative to.
+    pub fn apply(&self, root: &Geometry, area: &Geometry) -> Geometry {
+        let overlaps = |(start, end): (u32, u32), from: u32, len: u32| {
+            len > 0 && start < from + len && end >= from
+        };
+
+        let mut left = 0;
+        let mut right = 0;
+        let mut top = 0;
+        let mut bottom = 0;
+
+        if overlaps(self.left_range, area.y(), area.height()) {
+            left = self.left.saturating_sub(area.x());
+        }
+
+        if overlaps(self.right_range, area.y(), area.height()) {
+            let edge = root.width().saturating_sub(self.right);
+            right = (area.x() + area.width()).saturating_sub(edge);
+        }
+
+        if overlaps(self.top_range, area.x(), area.width()) {
+            top = self.top.saturating_sub(area.y());
+        }
+
+        if overlaps(self.bottom_range, area.x(), area.width()) {
+            let edge = root.height().saturating_sub(self.bottom);
+            bottom = (area.y() + area.height()).saturating_sub(edge);
+        }
+
+        let left = left.min(area.width());
+        let top = top.min(area.height());
+
+        Geometry::new(area.x() + left,
+                      area.y() + top,
+                      area.width().saturating_sub(left + right),
+                      area.height().saturating_sub(top + bottom))
+    }
+}
+
+/// The protocols from `WM_PROTOCOLS` the window manager knows about.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Protocols {
+    /// `WM_DELETE_WINDOW` is supported.
+    pub delete_window: bool,
+    /// `WM_TAKE_FOCUS` is supported.
+    pub take_focus: bool,
+}
+
+/// A window type from `_NET_WM_WINDOW_TYPE`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum WindowType {
+    Desktop,
+    Dock,
+    Toolbar,
+    Menu,
+    Utility,
+    Splash,
+    Dialog,
+    Notification,
+    Normal,
+    /// A window type not known to the window manager.
+    Other(xproto::Atom),
+}
+
+impl WindowType {
+    /// Get the name of the window type, without the `_NET_WM_WINDOW_TYPE_` prefix, in lower
+    /// case.
+    ///
+    /// Returns `None` for window types not known to the window manager.
+    pub fn name(&self) -> Option<&'static str> {
+        match self {
+            WindowType::Desktop => Some("desktop"),
+            WindowType::Dock => Some("dock"),
+            WindowType::Toolbar => Some("toolbar"),
+            WindowType::Menu => Some("menu"),
+            WindowType::Utility => Some("utility"),
+            WindowType::Splash => Some("splash"),
+            WindowType::Dialog => Some("dialog"),
+            WindowType::Notification => Some("notification"),
+            WindowType::Normal => Some("normal"),
+            WindowType::Other(_) => None,
+        }
+    }
+
+    /// Look up a window type by the name returned by `name`.
+    pub fn from_name(name: &str) -> Option<WindowType> {
+        match name {
+            "desktop" => Some(WindowType::Desktop),
+            "dock" => Some(WindowType::Dock),
+            "toolbar" => Some(WindowType::Toolbar),
+            "menu" => Some(WindowType::Menu),
+            "utility" => Some(WindowType::Utility),
+            "splash" => Some(WindowType::Splash),
+            "dialog" => Some(WindowType::Dialog),
+            "notification" => Some(WindowType::Notification),
+            "normal" => Some(WindowType::Normal),
+            _ => None,
+        }
+    }
+
+    fn from_atom(atoms: &Atoms, atom: xproto::Atom) -> WindowType {
+        match atom {
+            a if a == atoms.net_wm_window_type_desktop => WindowType::Desktop,
+            a if a == atoms.net_wm_window_type_dock => WindowType::Dock,
+            a if a == atoms.net_wm_window_type_toolbar => WindowType::Toolbar,
+            a if a == atoms.net_wm_window_type_menu => WindowType::Menu,
+            a if a == atoms.net_wm_window_type_utility => WindowType::Utility,
+            a if a == atoms.net_wm_window_type_splash => WindowType::Splash,
+            a if a == atoms.net_wm_window_type_dialog => WindowType::Dialog,
+            a if a == atoms.net_wm_window_type_notification => WindowType::Notification,
+            a if a == atoms.net_wm_window_type_normal => WindowType::Normal,
+            a => WindowType::Other(a),
+        }
+    }
+}
+
+/// A window state from `_NET_WM_STATE`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum WindowState {
+    Modal,
+    Sticky,
+    Hidden,
+    Fullscreen,
+    Above,
+    Below,
+    DemandsAttention,
+    /// A window state not known to the window manager.
+    Other(xproto::Atom),
+}
+
+impl WindowState {
+    pub fn from_atom(atoms: &Atoms, atom: xproto::Atom) -> WindowState {
+        match atom {
+            a if a == atoms.net_wm_state_modal => WindowState::Modal,
+            a if a == atoms.net_wm_state_sticky => WindowState::Sticky,
+            a if a == atoms.net_wm_state_hidden => WindowState::Hidden,
+            a if a == atoms.net_wm_state_fullscreen => WindowState::Fullscreen,
+            a if a == atoms.net_wm_state_above => WindowState::Above,
+            a if a == atoms.net_wm_state_below => WindowState::Below,
+            a if a == atoms.net_wm_state_demands_attention => WindowState::DemandsAttention,
+            a => WindowState::Other(a),
+        }
+    }
+
+    pub fn to_atom(self, atoms: &Atoms) -> xproto::Atom {
+        match self {
+            WindowState::Modal => atoms.net_wm_state_modal,
+            WindowState::Sticky => atoms.net_wm_state_sticky,
+            WindowState::Hidden => atoms.net_wm_state_hidden,
+            WindowState::Fullscreen => atoms.net_wm_state_fullscreen,
+            WindowState::Above => atoms.net_wm_state_above,
+            WindowState::Below => atoms.net_wm_state_below,
+            WindowState::DemandsAttention => atoms.net_wm_state_demands_attention,
+            WindowState::Other(a) => a,
+        }
+    }
+}
+
+/// The ICCCM and EWMH properties of a client, as cached by the window manager.
+#[derive(Clone, Debug, Default)]
+pub struct ClientProperties {
+    /// The client's `WM_CLASS`.
+    pub class: Option<WmClass>,
+    /// The client's `WM_NAME`.
+    pub wm_name: Option<String>,
+    /// The client's `_NET_WM_NAME`.
+    pub net_wm_name: Option<String>,
+    /// The client's `WM_HINTS`.
+    pub hints: Option<WmHints>,
+    /// The client's `WM_NORMAL_HINTS`.
+    pub size_hints: Option<SizeHints>,
+    /// The protocols the client participates in.
+    pub protocols: Protocols,
+    /// The window the client is transient for.
+    pub transient_for: Option<xproto::Window>,
+    /// The client's window types, in order of preference.
+    pub window_type: Vec<WindowType>,
+    /// The client's window states.
+    pub state: Vec<WindowState>,
+    /// The process id of the client.
+    pub pid: Option<u32>,
+    /// The client's `_NET_WM_STRUT`.
+    pub strut: Option<Strut>,
+    /// The client's `_NET_WM_STRUT_PARTIAL`.
+    pub strut_partial: Option<Strut>,
+}
+
+impl ClientProperties {
+    /// Fetch all properties of a client window.
+    ///
+    /// All requests are sent before waiting for any reply.
+    pub fn fetch(con: &Connection, atoms: &Atoms, window: xproto::Window) -> ClientProperties {
+        let cookies: Vec<_> = PROPERTIES
+            .iter()
+            .map(|p| (*p, Self::request(con, atoms, window, *p)))
+            .collect();
+
+        let mut properties = ClientProperties::default();
+
+        for (property, cookie) in cookies {
+            properties.apply(atoms, property, cookie);
+        }
+
+        properties
+    }
+
+    /// Update a property after it has been changed.
+    ///
+    /// Returns the property updated, or `None` if the atom doesn't name a cached property.
+    pub fn update(&mut self,
+                  con: &Connection,
+                  atoms: &Atoms,
+                  window: xproto::Window,
+                  atom: xproto::Atom) -> Option<Property>
+    {
+        let property = Property::from_atom(atoms, atom)?;
+        let cookie = Self::request(con, atoms, window, property);
+
+        self.apply(atoms, property, cookie);
+
+        Some(property)
+    }
+
+    /// Get the title of the client, preferring `_NET_WM_NAME` over `WM_NAME`.
+    pub fn title(&self) -> &str {
+        self.net_wm_name
+            .as_ref()
+            .or(self.wm_name.as_ref())
+            .map_or("", |t| t.as_str())
+    }
+
+    /// Get the space reserved by the client, preferring `_NET_WM_STRUT_PARTIAL`.
+    pub fn reserved(&self) -> Option<Strut> {
+        self.strut_partial.or(self.strut)
+    }
+
+    /// Get the input model of the client.
+    ///
+    /// Clients not specifying the input hint are assumed to expect the input focus.
+    pub fn input_model(&self) -> InputModel {
+        let input = self.hints.and_then(|h| h.input).unwrap_or(true);
+
+        match (input, self.protocols.take_focus) {
+            (false, false) => InputModel::NoInput,
+            (true, false) => InputModel::Passive,
+            (true, true) => InputModel::LocallyActive,
+            (false, true) => InputModel::GloballyActive,
+        }
+    }
+
+    /// Check whether the client has a given window type.
+    pub fn has_type(&self, window_type: WindowType) -> bool {
+        self.window_type.contains(&window_type)
+    }
+
+    /// Check whether the client has a given window state.
+    pub fn has_state(&self, state: WindowState) -> bool {
+        self.state.contains(&state)
+    }
+
+    fn request<'a>(con: &'a Connection,
+                   atoms: &Atoms,
+                   window: xproto::Window,
+                   property: Property) -> xproto::GetPropertyCookie<'a>
+    {
+        xproto::get_property(con,
+                             false,
+                             window,
+                             property.atom(atoms),
+                             xproto::ATOM_ANY,
+                             0,
+                             1024)
+    }
+
+    /// Store the value of a property from the reply to a request.
+    ///
+    /// Missing or malformed properties are reset to their defaults.
+    fn apply(&mut self, atoms: &Atoms, property: Property, cookie: xproto::GetPropertyCookie) {
+        let reply = cookie.get_reply().ok().filter(|r| r.value_len() > 0);
+
+        match property {
+            Property::WmClass => {
+                self.class = reply.map(|r| parse_class(r.value()));
+            },
+            Property::WmName => {
+                self.wm_name = reply.map(|r| String::from_utf8_lossy(r.value()).into_owned());
+            },
+            Property::NetWmName => {
+                self.net_wm_name = reply.map(|r| String::from_utf8_lossy(r.value()).into_owned());
+            },
+            Property::WmHints => {
+                self.hints = reply.and_then(|r| parse_hints(values(&r)));
+            },
+            Property::WmNormalHints => {
+                self.size_hints = reply.and_then(|r| parse_size_hints(values(&r)));
+            },
+            Property::WmProtocols => {
+                let protocols = reply.as_ref().map_or(&[][..], values);
+
+                self.protocols = Protocols {
+                    delete_window: protocols.contains(&atoms.wm_delete_window),
+                    take_focus: protocols.contains(&atoms.wm_take_focus),
+                };
+            },
+            Property::WmTransientFor => {
+                self.transient_for = reply
+                    .and_then(|r| values(&r).first().cloned())
+                    .filter(|w| *w != NONE);
+            },
+            Property::NetWmWindowType => {
+                self.window_type = reply.as_ref().map_or(&[][..], values)
+                    .iter()
+                    .map(|a| WindowType::from_atom(atoms, *a))
+                    .collect();
+            },
+            Property::NetWmState => {
+                self.state = reply.as_ref().map_or(&[][..], values)
+                    .iter()
+                    .map(|a| WindowState::from_atom(atoms, *a))
+                    .collect();
+            },
+            Property::NetWmPid => {
+                self.pid = reply.and_then(|r| values(&r).first().cloned());
+            },
+            Property::NetWmStrut => {
+                self.strut = reply.and_then(|r| parse_strut(values(&r)));
+            },
+            Property::NetWmStrutPartial => {
+                self.strut_partial = reply.and_then(|r| parse_strut(values(&r)));
+            },
+        }
+    }
+}
+
+/// Get the values of a 32 bit property.
+fn values(reply: &xproto::GetPropertyReply) -> &[u32] {
+    if reply.format() == 32 {
+        reply.value()
+    } else {
+        &[]
+    }
+}
+
+/// Parse a `WM_CLASS` property consisting of two null-terminated strings.
+fn parse_class(value: &[u8]) -> WmClass {
+    let mut parts = value
+        .split(|b| *b == 0)
+        .map(|s| String::from_utf8_lossy(s).into_owned());
+
+    WmClass {
+        instance: parts.next().unwrap_or_default(),
+        class: parts.next().unwrap_or_default(),
+    }
+}
+
+/// Parse a `WM_HINTS` property.
+fn parse_hints(value: &[u32]) -> Option<WmHints> {
+    const INPUT_HINT: u32 = 1;
+    const STATE_HINT: u32 = 1 << 1;
+    const WINDOW_GROUP_HINT: u32 = 1 << 6;
+    const URGENCY_HINT: u32 = 1 << 8;
+
+    if value.len() < 9 {
+        return None;
+    }
+
+    let flags = value[0];
+    let flag = |f: u32, v: u32| if flags & f != 0 { Some(v) } else { None };
+
+    Some(WmHints {
+        input: flag(INPUT_HINT, value[1]).map(|i| i != 0),
+        initial_state: flag(STATE_HINT, value[2]),
+        urgent: flags & URGENCY_HINT != 0,
+        window_group: flag(WINDOW_GROUP_HINT, value[8]),
+    })
+}
+
+/// Parse a `WM_NORMAL_HINTS` property.
+fn parse_size_hints(value: &[u32]) -> Option<SizeHints> {
+    const P_MIN_SIZE: u32 = 1 << 4;
+    const P_MAX_SIZE: u32 = 1 << 5;
+    const P_RESIZE_INC: u32 = 1 << 6;
+    const P_ASPECT: u32 = 1 << 7;
+    const P_BASE_SIZE: u32 = 1 << 8;
+
+    if value.len() < 17 {
+        return None;
+    }
+
+    let flags = value[0];
+    let pair = |f: u32, i: usize| {
+        if flags & f != 0 { Some((value[i], value[i + 1])) } else { None }
+    };
+
+    Some(SizeHints {
+        min: pair(P_MIN_SIZE, 5),
+        max: pair(P_MAX_SIZE, 7),
+        inc: pair(P_RESIZE_INC, 9),
+        aspect: pair(P_ASPECT, 11).and_then(|min| pair(P_ASPECT, 13).map(|max| (min, max))),
+        base: pair(P_BASE_SIZE, 15),
+    })
+}
+
+/// Parse a `_NET_WM_STRUT` or `_NET_WM_STRUT_PARTIAL` property.
+///
+/// The widths of `_NET_WM_STRUT` apply to entire edges.
+fn parse_strut(value: &[u32]) -> Option<Strut> {
+    let full = (0, u32::MAX);
+
+    match value.len() {
+        4 => Some(Strut {
+            left: value[0],
+            right: value[1],
+            top: value[2],
+            bottom: value[3],
+            left_range: full,
+            right_range: full,
+            top_range: full,
+            bottom_range: full,
+        }),
+        n if n >= 12 => Some(Strut {
+            left: value[0],
+            right: value[1],
+            top: value[2],
+            bottom: value[3],
+            left_range: (value[4], value[5]),
+            right_range: (value[6], value[7]),
+            top_range: (value[8], value[9]),
+            bottom_range: (value[10], value[11]),
+        }),
+        _ => None,
+    }
+}
@@ -0,0 +1,268 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::Path;
+
+use regex::Regex;
+
+use toml::value::{Array, Table, Value};
+
+use xcb::xproto;
+
+use config::Tag;
+use criteria::{Criteria, Pattern};
+use err::*;
+use layout::Geometry;
+use properties::{ClientProperties, WindowType};
+
+/// The actions taken for a new client, as determined by the rules matching it.
+///
+/// Fields that are `None` have not been set by any rule.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Actions {
+    /// The tags to assign to the client.
+    pub tags: Option<HashSet<Tag>>,
+    /// Whether the client is floating.
+    pub floating: Option<bool>,
+    /// The index of the screen whose tags are assigned to the client, if none are given.
+    pub screen: Option<usize>,
+    /// The geometry of the client when floating.
+    pub geometry: Option<Geometry>,
+    /// Whether the client is focused when it appears.
+    pub focus: Option<bool>,
+}
+
+impl Actions {
+    /// Override the actions set by another set of actions.
+    fn merge(&mut self, other: &Actions) {
+        if other.tags.is_some() {
+            self.tags = other.tags.clone();
+        }
+
+        self.floating = other.floating.or(self.floating);
+        self.screen = other.screen.or(self.screen);
+        self.geometry = other.geometry.or(self.geometry);
+        self.focus = other.focus.or(self.focus);
+    }
+}
+
+/// A rule, taking actions for new clients meeting its criteria.
+#[derive(Debug)]
+pub struct Rule {
+    criteria: Criteria,
+    actions: Actions,
+}
+
+/// The set of rules applied to new clients.
+#[derive(Debug, Default)]
+pub struct Rules {
+    rules: Vec<Rule>,
+}
+
+impl Rules {
+    /// Parse the rules from a configuration file.
+    ///
+    /// The file contains an array of tables named `rules`, each holding the criteria and
+    /// actions of a rule:
+    ///
+    /// ```toml
+    /// [[rules]]
+    /// class = "Firefox"
+    /// title = "^Picture-in-Picture$"
+    /// floating = true
+    /// geometry = [0, 0, 640, 360]
+    /// ```
+    ///
+    /// Criteria are `class`, `instance`, `title` (a regular expression), `type` (a window
+    /// type such as `"dialog"`) and `transient`. They can also be given in the bracketed form
+    /// accepted by control requests, as in `criteria = '[class="Firefox" title="~^Meet"]'`,
+    /// which the other keys add to. Tags and urgency can't be matched, since new clients have
+    /// neither. Actions are `tags` (work tags given by
+    /// number, or `["nonwork"]`), `floating`, `screen`, `geometry` (`[x, y, width, height]`)
+    /// and `focus`.
+    pub fn from_file(path: &Path) -> WmResult<Rules> {
+        let mut toml_str = String::new();
+
+        File::open(path)
+            .and_then(|mut f| f.read_to_string(&mut toml_str))
+            .map_err(WmError::IOError)?;
+
+        let mut table = match toml_str.parse::<Value>() {
+            Ok(Value::Table(t)) => t,
+            Ok(_) => return Err(WmError::TomlNotTable),
+            Err(e) => return Err(WmError::TomlError(e)),
+        };
+
+        let rules = match table.remove("rules") {
+            Some(Value::Array(a)) => a,
+            Some(_) => return Err(WmError::KeyTypeMismatch("rules".to_owned())),
+            None => return Ok(Rules::default()),
+        };
+
+        let rules = rules
+            .into_iter()
+            .enumerate()
+            .map(|(i, rule)| match rule {
+                Value::Table(t) => Self::parse_rule(i, t),
+                _ => Err(WmError::KeyTypeMismatch(format!("rules.{}", i))),
+            })
+            .collect::<WmResult<_>>()?;
+
+        Ok(Rules { rules })
+    }
+
+    /// Determine the actions to take for a new client.
+    ///
+    /// All matching rules are applied in order, later rules overriding the actions of
+    /// earlier ones.
+    pub fn apply(&self, window: xproto::Window, properties: &ClientProperties) -> Actions {
+        let mut actions = Actions::default();
+
+        for rule in self.rules
+            .iter()
+            .filter(|r| r.criteria.matches_properties(window, properties))
+        {
+            actions.merge(&rule.actions);
+        }
+
+        actions
+    }
+
+    fn parse_rule(index: usize, mut table: Table) -> WmResult<Rule> {
+        let key = |k: &str| format!("rules.{}.{}", index, k);
+
+        let mut criteria = match extract_string(&mut table, &key("criteria"), "criteria")? {
+            Some(c) => Criteria::parse(&c)
+                .map_err(|e| WmError::InvalidCriteria(key("criteria"), e))?,
+            None => Criteria::default(),
+        };
+
+        if !criteria.only_properties() {
+            return Err(WmError::InvalidCriteria(
+                key("criteria"), "tags and urgency can't be matched by rules".to_owned()));
+        }
+
+        if let Some(c) = extract_string(&mut table, &key("class"), "class")? {
+            criteria.class = Some(Pattern::Exact(c));
+        }
+
+        if let Some(i) = extract_string(&mut table, &key("instance"), "instance")? {
+            criteria.instance = Some(Pattern::Exact(i));
+        }
+
+        if let Some(t) = extract_string(&mut table, &key("title"), "title")? {
+            let regex = Regex::new(&t).map_err(|e| WmError::InvalidRegex(t, e))?;
+            criteria.title = Some(Pattern::Regex(regex));
+        }
+
+        if let Some(t) = extract_string(&mut table, &key("type"), "type")? {
+            let window_type = WindowType::from_name(&t)
+                .ok_or_else(|| WmError::InvalidValue(key("type"), t))?;
+            criteria.window_type = Some(window_type);
+        }
+
+        if let Some(t) = extract_bool(&mut table, &key("transient"), "transient")? {
+            criteria.transient = Some(t);
+        }
+
+        let tags = match extract_array(&mut table, &key("tags"), "tags")? {
+            Some(tags) => Some(tags
+                .into_iter()
+                .map(|t| parse_tag(&t)
+                     .ok_or_else(|| WmError::InvalidValue(key("tags"), t.to_string())))
+                .collect::<WmResult<_>>()?),
+            None => None,
+        };
+
+        let screen = match extract_int(&mut table, &key("screen"), "screen")? {
+            Some(s) if s >= 0 => Some(s as usize),
+            Some(s) => return Err(WmError::InvalidValue(key("screen"), s.to_string())),
+            None => None,
+        };
+
+        let geometry = match extract_array(&mut table, &key("geometry"), "geometry")? {
+            Some(g) => match parse_geometry(&g) {
+                Some(geometry) => Some(geometry),
+                None => {
+                    let value = Value::Array(g).to_string();
+                    return Err(WmError::InvalidValue(key("geometry"), value));
+                },
+            },
+            None => None,
+        };
+
+        let actions = Actions {
+            tags,
+            floating: extract_bool(&mut table, &key("floating"), "floating")?,
+            screen,
+            geometry,
+            focus: extract_bool(&mut table, &key("focus"), "focus")?,
+        };
+
+        if let Some(k) = table.keys().next() {
+            return Err(WmError::UnknownKey(key(k)));
+        }
+
+        Ok(Rule { criteria, actions })
+    }
+}
+
+/// Extract an optional key's value from a table as a string.
+fn extract_string(table: &mut Table, path: &str, key: &str) -> WmResult<Option<String>> {
+    match table.remove(key) {
+        Some(Value::String(s)) => Ok(Some(s)),
+        Some(_) => Err(WmError::KeyTypeMismatch(path.to_owned())),
+        None => Ok(None),
+    }
+}
+
+/// Extract an optional key's value from a table as a boolean.
+fn extract_bool(table: &mut Table, path: &str, key: &str) -> WmResult<Option<bool>> {
+    match table.remove(key) {
+        Some(Value::Boolean(b)) => Ok(Some(b)),
+        Some(_) => Err(WmError::KeyTypeMismatch(path.to_owned())),
+        None => Ok(None),
+    }
+}
+
+/// Extract an optional key's value from a table as an int.
+fn extract_int(table: &mut Table, path: &str, key: &str) -> WmResult<Option<i64>> {
+    match table.remove(key) {
+        Some(Value::Integer(i)) => Ok(Some(i)),
+        Some(_) => Err(WmError::KeyTypeMismatch(path.to_owned())),
+        None => Ok(None),
+    }
+}
+
+/// Extract an optional key's value from a table as an array.
+fn extract_array(table: &mut Table, path: &str, key: &str) -> WmResult<Option<Array>> {
+    match table.remove(key) {
+        Some(Value::Array(a)) => Ok(Some(a)),
+        Some(_) => Err(WmError::KeyTypeMismatch(path.to_owned())),
+        None => Ok(None),
+    }
+}
+
+/// Parse a tag, given as a number for work tags, or as `"nonwork"`.
+fn parse_tag(value: &Value) -> Option<Tag> {
+    match value {
+        Value::Integer(i) if *i >= i64::from(i8::MIN) && *i <= i64::from(i8::MAX) =>
+            Some(Tag::Work(*i as i8)),
+        Value::String(s) if s == "nonwork" => Some(Tag::NonWork),
+        _ => None,
+    }
+}
+
+/// Parse a geometry given as `[x, y, width, height]`.
+fn parse_geometry(values: &[Value]) -> Option<Geometry> {
+    let values: Vec<_> = values
+        .iter()
+        .map(|v| v.as_integer().filter(|i| *i >= 0 && *i <= i64::from(u32::MAX)))
+        .collect::<Option<_>>()?;
+
+    if let [x, y, width, height] = values[..] {
+        Some(Geometry::new(x as u32, y as u32, width as u32, height as u32))
+    } else {
+        None
+    }
+}
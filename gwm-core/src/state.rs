@@ -0,0 +1,1971 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::io::{self, Write};
+use std::mem;
+use std::os::unix::io::AsRawFd;
+use std::time::{Duration, Instant};
+
+use libc;
+
+use xcb::base::*;
+use xcb::xproto;
+
+use atoms::Atoms;
+use config::{self, Tag};
+use criteria::{Criteria, Search};
+use err::*;
+use ewmh::{self, ClientMessage, Ewmh, MoveResize};
+use gwm_ipc::{self, ErrorKind, Event, Reply, Request, Response};
+use ipc::IpcServer;
+use layout::{ClientSizes, Direction, Geometry, Layout, LayoutMessage, Manual, TitleAreas};
+use properties::{ClientProperties, InputModel, Property, WindowState, WindowType};
+use rules::Rules;
+use titles::TitleStrips;
+use tree::*;
+
+/// The state observed by clients subscribed to events, as last published.
+#[derive(Default)]
+struct Snapshot {
+    /// The tags shown on each screen.
+    tags: Vec<Vec<String>>,
+    /// The arrangement of the clients shown on each screen.
+    arrangement: Vec<HashMap<xproto::Window, Geometry>>,
+    /// The focused screen and client.
+    focus: (usize, Option<xproto::Window>),
+    /// The titles of all clients.
+    titles: HashMap<xproto::Window, String>,
+    /// The urgent clients.
+    urgent: HashSet<xproto::Window>,
+}
+
+impl Snapshot {
+    /// Capture the state of a client hierarchy.
+    ///
+    /// `visible` holds the geometries of all clients shown.
+    fn new(hierarchy: &ClientHierarchy<xproto::Window>,
+           visible: &HashMap<xproto::Window, Geometry>) -> Self {
+        let mut snapshot = Snapshot::default();
+
+        for screen in hierarchy.screens() {
+            let tagset = hierarchy.get_tagset(screen.tagset());
+
+            snapshot.tags.push(tagset
+                .map_or_else(Vec::new, |t| t.tags().iter().map(|t| t.to_string()).collect()));
+            snapshot.arrangement.push(tagset
+                .map_or_else(HashMap::new, |t| t
+                    .tree()
+                    .preorder(ContainerId::Root)
+                    .filter_map(|(id, _)| t.tree().get_client(id))
+                    .filter_map(|w| visible.get(w).map(|g| (*w, *g)))
+                    .collect()));
+        }
+
+        snapshot.focus =
+            (hierarchy.get_focused_screen_index(), hierarchy.get_focused_client().cloned());
+
+        for client in hierarchy.clients() {
+            snapshot.titles.insert(*client.id(), client.properties().title().to_owned());
+
+            if client.is_urgent() {
+                snapshot.urgent.insert(*client.id());
+            }
+        }
+
+        snapshot
+    }
+
+    /// Compute the events describing the changes from an older snapshot to this one.
+    fn events_since(&self, old: &Snapshot) -> Vec<Event> {
+        let mut events = Vec::new();
+
+        for screen in self.tags.len()..old.tags.len() {
+            events.push(Event::Screen { screen, added: false });
+        }
+
+        for (screen, tags) in self.tags.iter().enumerate() {
+            if screen >= old.tags.len() {
+                events.push(Event::Screen { screen, added: true });
+            }
+
+            if old.tags.get(screen) != Some(tags) {
+                events.push(Event::Tagset { screen, tags: tags.clone() });
+            } else if old.arrangement.get(screen) != self.arrangement.get(screen) {
+                events.push(Event::Layout { screen });
+            }
+        }
+
+        if self.focus != old.focus {
+            let (screen, window) = self.focus;
+            events.push(Event::Focus { screen, window });
+        }
+
+        for (window, title) in &self.titles {
+            if old.titles.get(window) != Some(title) {
+                events.push(Event::Title { window: *window, title: title.clone() });
+            }
+        }
+
+        for window in self.urgent.symmetric_difference(&old.urgent) {
+            if self.titles.contains_key(window) {
+                let urgent = self.urgent.contains(window);
+                events.push(Event::Urgency { window: *window, urgent });
+            }
+        }
+
+        events
+    }
+}
+
+/// Global window manager state object.
+pub struct WmState<'a> {
+    /// X connection used to communicate.
+    con: &'a Connection,
+    /// Root window.
+    root: xproto::Window,
+    /// The atoms interned at startup.
+    atoms: Atoms,
+    /// The hierarchy of all screens, tagsets and clients.
+    hierarchy: ClientHierarchy<xproto::Window>,
+    /// The title strips drawn above tabbed and stacked containers.
+    titles: TitleStrips<'a>,
+    /// The rules applied to new clients.
+    rules: Rules,
+    /// The EWMH properties published on the root window.
+    ewmh: Ewmh<'a>,
+    /// All managed clients, in the order they have been managed.
+    client_list: Vec<xproto::Window>,
+    /// Floating transient clients centered on their parent, mapped to the parent.
+    transients: HashMap<xproto::Window, xproto::Window>,
+    /// The status line last printed.
+    status: String,
+    /// The geometry of the root window.
+    root_geometry: Geometry,
+    /// The dock windows, which are kept mapped but not managed, with their properties.
+    docks: HashMap<xproto::Window, ClientProperties>,
+    /// Clients asked to close, with the time at which they are killed if still present.
+    pending_kills: HashMap<xproto::Window, Instant>,
+    /// The client last given the input focus, if any.
+    input_focus: Option<xproto::Window>,
+    /// The time of the last event received carrying a timestamp.
+    timestamp: xproto::Timestamp,
+    /// The sequence number of the request marking the end of the last layout change.
+    ///
+    /// Enter events generated before it has been processed are caused by windows moving
+    /// around, and not by the pointer, so they are ignored.
+    enter_marker: u16,
+    /// The control socket, if it could be bound.
+    ipc: Option<IpcServer>,
+    /// The state last published to clients subscribed to events.
+    snapshot: Snapshot,
+    /// The events not yet sent to clients subscribed to events.
+    events: Vec<Event>,
+}
+
+impl<'a> WmState<'a> {
+    /// Construct a window manager state object applying the given rules to new clients.
+    ///
+    /// Registers as the window manager on the root window, which fails if another window
+    /// manager is running. The screen is added to the client hierarchy with a tagset showing
+    /// the configured initial tags.
+    pub fn new(con: &'a Connection, screen_num: i32, rules: Rules) -> WmResult<Self> {
+        let screen = if let Some(screen) = con.get_setup().roots().nth(screen_num as usize) {
+            screen
+        } else {
+            return Err(XError::CouldNotAcquireScreen.wrap());
+        };
+
+        let values = [(
+            xproto::CW_EVENT_MASK,
+            xproto::EVENT_MASK_SUBSTRUCTURE_REDIRECT | xproto::EVENT_MASK_SUBSTRUCTURE_NOTIFY,
+        )];
+
+        if xproto::change_window_attributes_checked(con, screen.root(), &values)
+            .request_check()
+            .is_err()
+        {
+            return Err(XError::OtherWmRunning.wrap());
+        }
+
+        let atoms = Atoms::intern(con)?;
+        let ewmh = Ewmh::new(con, screen.root(), &atoms, config::EWMH_DESKTOPS)?;
+
+        if config::EWMH_DESKTOPS {
+            let names: Vec<_> = config::TAGS.iter().map(|t| t.to_string()).collect();
+            ewmh.set_desktops(&atoms, &names);
+        }
+        let titles = TitleStrips::new(con, screen_num, config::TITLES)?;
+
+        let mut hierarchy = ClientHierarchy::new();
+
+        let tagset = hierarchy.add_tagset(TagSet::new(
+            config::INITIAL_TAGS.iter().cloned().collect(),
+            TagTree::new(SplitType::Vertical(SplitRatio::default())),
+            Self::new_layout(&titles)));
+        let geometry = Geometry::new(
+            0, 0, u32::from(screen.width_in_pixels()), u32::from(screen.height_in_pixels()));
+        hierarchy.add_screen(Screen::new(geometry, tagset));
+
+        for scratchpad in config::SCRATCHPADS {
+            let classes = scratchpad.classes.iter().map(|c| (*c).to_owned()).collect();
+            hierarchy.add_scratchpad(scratchpad.name, classes);
+        }
+
+        // the window manager remains usable without the control socket
+        let ipc = match IpcServer::bind() {
+            Ok(server) => Some(server),
+            Err(e) => {
+                warn!("could not bind control socket: {}", e);
+                None
+            },
+        };
+
+        Ok(WmState {
+            con,
+            root: screen.root(),
+            atoms,
+            hierarchy,
+            titles,
+            rules,
+            ewmh,
+            client_list: Vec::new(),
+            transients: HashMap::new(),
+            status: String::new(),
+            root_geometry: geometry,
+            docks: HashMap::new(),
+            pending_kills: HashMap::new(),
+            input_focus: None,
+            timestamp: xproto::TIME_CURRENT_TIME,
+            enter_marker: 0,
+            ipc,
+            snapshot: Snapshot::default(),
+            events: Vec::new(),
+        })
+    }
+
+    /// Create the layout used for new tagsets.
+    fn new_layout(titles: &TitleStrips) -> Box<Layout<xproto::Window>> {
+        Box::new(Manual { title_height: titles.title_height() })
+    }
+
+    /// Manage all windows already mapped.
+    pub fn setup(&mut self) -> WmResult<()> {
+        let children = match xproto::query_tree(self.con, self.root).get_reply() {
+            Ok(reply) => reply.children().to_vec(),
+            Err(e) => return Err(XError::RequestFailed(e).wrap()),
+        };
+
+        let cookies: Vec<_> = children
+            .iter()
+            .map(|w| (*w, xproto::get_window_attributes(self.con, *w)))
+            .collect();
+
+        for (window, cookie) in cookies {
+            if let Ok(attrs) = cookie.get_reply() {
+                if attrs.map_state() == xproto::MAP_STATE_VIEWABLE as u8 {
+                    self.manage(window, true);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Start managing a window.
+    ///
+    /// Override-redirect windows are ignored. The window's properties are fetched and the
+    /// rules are applied to determine its placement. Unless the rules assign tags, transient
+    /// clients inherit the tags of their parent, and other clients get the tags of the tagset
+    /// shown on the target screen (by default the focused one), or are moved to a scratchpad
+    /// if their class is configured for one. Transient clients and dialogs float by default.
+    /// Docks are mapped, but not managed. `mapped` signifies whether the window is already
+    /// mapped.
+    fn manage(&mut self, window: xproto::Window, mapped: bool) {
+        if self.hierarchy.get_client(&window).is_some() || self.docks.contains_key(&window) {
+            return;
+        }
+
+        match xproto::get_window_attributes(self.con, window).get_reply() {
+            Ok(ref attrs) if !attrs.override_redirect() => (),
+            _ => return,
+        }
+
+        let event_mask = if config::FOCUS_FOLLOWS_MOUSE {
+            xproto::EVENT_MASK_PROPERTY_CHANGE | xproto::EVENT_MASK_ENTER_WINDOW
+        } else {
+            xproto::EVENT_MASK_PROPERTY_CHANGE
+        };
+
+        xproto::change_window_attributes(self.con, window, &[
+            (xproto::CW_EVENT_MASK, event_mask),
+        ]);
+
+        let properties = ClientProperties::fetch(self.con, &self.atoms, window);
+
+        if properties.has_type(WindowType::Dock) {
+            self.add_dock(window, properties, mapped);
+            return;
+        }
+
+        let actions = self.rules.apply(window, &properties);
+        debug!("managing window {}: {:?}, {:?}", window, properties, actions);
+
+        let parent = properties.transient_for.filter(|p| self.hierarchy.get_client(p).is_some());
+
+        let placed = actions.tags.is_some() || actions.screen.is_some() || parent.is_some();
+
+        let scratchpad = if !placed {
+            properties.class
+                .as_ref()
+                .and_then(|c| self.hierarchy.scratchpad_for_class(&c.class))
+                .map(|s| s.to_owned())
+        } else {
+            None
+        };
+
+        let screen = actions.screen
+            .and_then(|s| self.hierarchy.screens().get(s))
+            .or_else(|| self.hierarchy.get_focused_screen());
+
+        let parent_tags = parent
+            .and_then(|p| self.hierarchy.get_client(&p))
+            .map(|c| c.tags().clone());
+
+        let tags: HashSet<_> = match (actions.tags, parent_tags, &scratchpad) {
+            (Some(tags), _, _) | (None, Some(tags), _) => tags,
+            (None, None, Some(_)) => HashSet::new(),
+            (None, None, None) => screen
+                .and_then(|s| self.hierarchy.get_tagset(s.tagset()))
+                .map_or_else(HashSet::new, |t| t.tags().iter().cloned().collect()),
+        };
+
+        let dialog = properties.transient_for.is_some() ||
+            properties.has_type(WindowType::Dialog) ||
+            properties.has_type(WindowType::Utility) ||
+            properties.has_type(WindowType::Splash);
+
+        let floating = actions.floating.unwrap_or(dialog);
+
+        // floating dialogs keep the size they requested
+        let geometry = actions.geometry.or_else(|| if floating && dialog {
+            xproto::get_geometry(self.con, window)
+                .get_reply()
+                .ok()
+                .map(|g| Geometry::new(0, 0, u32::from(g.width()), u32::from(g.height())))
+        } else {
+            None
+        });
+
+        if let (true, None, Some(parent)) = (floating, actions.geometry, parent) {
+            self.transients.insert(window, parent);
+        }
+
+        let placement = Placement {
+            floating,
+            geometry,
+            focus: actions.focus.unwrap_or(true),
+        };
+
+        let fullscreen = if properties.has_state(WindowState::Fullscreen) {
+            Some(FullscreenMode::Screen)
+        } else {
+            None
+        };
+
+        let mut client = Client::new(window, properties, tags);
+        client.set_mapped(mapped);
+        client.set_fullscreen(fullscreen);
+        self.hierarchy.add_client(client, &placement);
+        self.client_list.push(window);
+        self.publish_desktop(window);
+
+        if let Some(name) = scratchpad {
+            self.hierarchy.move_to_scratchpad(&name, window);
+        }
+
+        self.update_urgency(window);
+        self.arrange();
+    }
+
+    /// Map a dock window and reserve the space it requests.
+    fn add_dock(&mut self, window: xproto::Window, properties: ClientProperties, mapped: bool) {
+        debug!("adding dock {}: {:?}", window, properties.reserved());
+
+        self.docks.insert(window, properties);
+
+        if !mapped {
+            xproto::map_window(self.con, window);
+        }
+
+        self.update_reserved();
+        self.arrange();
+    }
+
+    /// Compute the area of each screen not reserved by docks.
+    fn update_reserved(&mut self) {
+        let areas: Vec<_> = self.hierarchy
+            .screens()
+            .iter()
+            .map(|screen| self.docks
+                 .values()
+                 .filter_map(|p| p.reserved())
+                 .fold(*screen.geometry(), |area, s| s.apply(&self.root_geometry, &area)))
+            .collect();
+
+        for (index, area) in areas.into_iter().enumerate() {
+            self.hierarchy.set_screen_area(index, area);
+        }
+    }
+
+    /// Stop managing a window, for instance because it has been withdrawn or destroyed.
+    ///
+    /// Docks are forgotten and the space they have reserved is freed.
+    fn unmanage(&mut self, window: xproto::Window) {
+        if self.docks.remove(&window).is_some() {
+            debug!("removed dock {}", window);
+            self.update_reserved();
+            self.arrange();
+            return;
+        }
+
+        if self.hierarchy.remove_client(&window).is_some() {
+            debug!("unmanaged window {}", window);
+            self.client_list.retain(|w| *w != window);
+            self.pending_kills.remove(&window);
+
+            if self.input_focus == Some(window) {
+                self.input_focus = None;
+            }
+
+            self.transients.remove(&window);
+            self.transients.retain(|_, p| *p != window);
+            self.arrange();
+        }
+    }
+
+    /// Get the height of title rows drawn above tabbed and stacked containers.
+    ///
+    /// Layouts need to be configured to reserve this much space per title.
+    pub fn title_height(&self) -> u32 {
+        self.titles.title_height()
+    }
+
+    /// Render the tagsets shown on all screens and apply the resulting geometries.
+    ///
+    /// Clients not rendered (for instance the invisible children of tabbed containers, or
+    /// clients on tagsets not shown at all) are unmapped.
+    pub fn arrange(&mut self) {
+        let mut visible = HashMap::new();
+        let mut floating = Vec::new();
+        let mut covering = Vec::new();
+        let mut fullscreen = Vec::new();
+        let mut shown = Vec::new();
+
+        let hierarchy = &self.hierarchy;
+
+        for screen in hierarchy.screens() {
+            let tagset = match hierarchy.get_tagset(screen.tagset()) {
+                Some(t) => t,
+                None => continue,
+            };
+
+            let mut sizes = ClientSizes::new();
+            let mut areas = TitleAreas::new();
+
+            tagset.layout().render(tagset.tree(), screen.area(), &mut sizes, &mut areas);
+
+            for (id, _) in tagset.tree().preorder(ContainerId::Root) {
+                let window = match tagset.tree().get_client(id) {
+                    Some(w) => *w,
+                    None => continue,
+                };
+
+                let geo = match sizes.get(&ContainerId::Index(id)) {
+                    Some(geo) => *geo,
+                    None => continue,
+                };
+
+                // fullscreen clients cover their screen or the container holding them, as
+                // long as the layout shows them
+                match hierarchy.get_client(&window).and_then(|c| c.fullscreen()) {
+                    Some(FullscreenMode::Screen) => {
+                        visible.insert(window, *screen.geometry());
+                        fullscreen.push(window);
+                    },
+                    Some(FullscreenMode::Container) => {
+                        let parent = tagset.tree().get_container(id).and_then(|c| c.get_parent());
+                        let area = match parent {
+                            Some(p @ ContainerId::Index(_)) => sizes.get(&p),
+                            _ => None,
+                        };
+
+                        visible.insert(window, *area.unwrap_or(screen.area()));
+                        covering.push(window);
+                    },
+                    None => {
+                        visible.insert(window, geo);
+
+                        if tagset.tree().is_floating(id) {
+                            floating.push(window);
+                        }
+                    },
+                }
+            }
+
+            self.titles.update(screen.tagset(), tagset.tree(), &areas, |w| {
+                hierarchy
+                    .get_client(w)
+                    .map_or_else(String::new, |c| c.properties().title().to_owned())
+            });
+            shown.push(screen.tagset());
+        }
+
+        self.titles.retain(&shown);
+
+        // transient clients are centered on their parent and kept above everything else
+        let mut transients = Vec::new();
+
+        for (window, parent) in &self.transients {
+            if let Some(parent_geo) = visible.get(parent).cloned() {
+                if let Some(geo) = visible.get_mut(window) {
+                    geo.center(&parent_geo);
+                    transients.push(*window);
+                }
+            }
+        }
+
+        floating.retain(|w| !transients.contains(w));
+
+        let windows: Vec<_> = self.hierarchy.clients().map(|c| *c.id()).collect();
+
+        for window in windows {
+            let client = self.hierarchy.get_client_mut(&window).unwrap();
+
+            if let Some(geo) = visible.get(&window) {
+                let geo = Self::constrain(client, geo);
+
+                xproto::configure_window(self.con, window, &[
+                    (xproto::CONFIG_WINDOW_X as u16, geo.x()),
+                    (xproto::CONFIG_WINDOW_Y as u16, geo.y()),
+                    (xproto::CONFIG_WINDOW_WIDTH as u16, geo.width()),
+                    (xproto::CONFIG_WINDOW_HEIGHT as u16, geo.height()),
+                ]);
+
+                if !client.is_mapped() {
+                    client.set_mapped(true);
+                    xproto::map_window(self.con, window);
+                }
+            } else if client.is_mapped() {
+                // mark the client first, so that the resulting unmap notify is ignored
+                client.set_mapped(false);
+                xproto::unmap_window(self.con, window);
+            }
+        }
+
+        // clients covering their container are kept above its other children, floating
+        // clients above tiled ones, and fullscreen clients above those
+        let raised = covering.iter().chain(&floating).chain(&fullscreen).chain(&transients);
+
+        for window in raised {
+            xproto::configure_window(self.con, *window, &[
+                (xproto::CONFIG_WINDOW_STACK_MODE as u16, xproto::STACK_MODE_ABOVE),
+            ]);
+        }
+
+        let focused = self.hierarchy
+            .get_focused_client()
+            .cloned()
+            .filter(|w| visible.contains_key(w));
+
+        self.focus(focused);
+
+        if let Some(window) = focused {
+            self.hierarchy.record_focus(&window);
+            self.clear_urgency(window);
+        }
+
+        let stacking: Vec<_> = self.client_list
+            .iter()
+            .filter(|w| !floating.contains(w) && !fullscreen.contains(w) && !transients.contains(w))
+            .chain(floating.iter())
+            .chain(fullscreen.iter())
+            .chain(transients.iter())
+            .cloned()
+            .collect();
+
+        self.ewmh.set_client_list(&self.atoms, &self.client_list, &stacking);
+        self.ewmh.set_active_window(&self.atoms, focused);
+
+        self.print_status();
+        self.queue_events(&visible);
+
+        if config::FOCUS_FOLLOWS_MOUSE {
+            self.enter_marker = xproto::no_operation(self.con).cookie.sequence as u16;
+        }
+
+        if config::EWMH_DESKTOPS {
+            let current = self.hierarchy
+                .get_focused_tagset()
+                .and_then(|t| self.hierarchy.get_tagset(t))
+                .and_then(|t| Self::desktop_of(t.tags().iter()));
+
+            if let Some(desktop) = current {
+                self.ewmh.set_current_desktop(&self.atoms, desktop);
+            }
+        }
+    }
+
+    /// Queue events describing the changes since the last call for subscribed clients.
+    ///
+    /// `visible` holds the geometries of all clients shown. The events are sent by
+    /// `send_events`, as the control socket is in use while requests are handled.
+    fn queue_events(&mut self, visible: &HashMap<xproto::Window, Geometry>) {
+        let snapshot = Snapshot::new(&self.hierarchy, visible);
+
+        self.events.extend(snapshot.events_since(&self.snapshot));
+        self.snapshot = snapshot;
+    }
+
+    /// Send the queued events to subscribed clients.
+    fn send_events(&mut self) {
+        let events = mem::take(&mut self.events);
+
+        if let Some(ref mut ipc) = self.ipc {
+            if !events.is_empty() && ipc.has_subscribers() {
+                ipc.broadcast(&events);
+            }
+        }
+    }
+
+    /// Give the input focus to a client according to its input model.
+    ///
+    /// Without a client to focus, the focus reverts to the root window. The input focus is
+    /// left where it is for clients that don't expect it, or take it themselves. Clients
+    /// supporting `WM_TAKE_FOCUS` are asked to take the focus whenever they become focused.
+    fn focus(&mut self, window: Option<xproto::Window>) {
+        let model = window
+            .and_then(|w| self.hierarchy.get_client(&w))
+            .map_or(InputModel::NoInput, |c| c.properties().input_model());
+
+        let target = match (window, model) {
+            (Some(window), InputModel::Passive) |
+            (Some(window), InputModel::LocallyActive) => Some(window),
+            (Some(_), _) => None,
+            (None, _) => Some(self.root),
+        };
+
+        if let Some(target) = target {
+            xproto::set_input_focus(self.con,
+                                    xproto::INPUT_FOCUS_POINTER_ROOT as u8,
+                                    target,
+                                    self.timestamp);
+        }
+
+        let changed = self.input_focus != window;
+        self.input_focus = window;
+
+        match (window, model) {
+            (Some(window), InputModel::LocallyActive) |
+            (Some(window), InputModel::GloballyActive) if changed => {
+                let data = xproto::ClientMessageData::from_data32([
+                    self.atoms.wm_take_focus,
+                    self.timestamp,
+                    0, 0, 0,
+                ]);
+                let event =
+                    xproto::ClientMessageEvent::new(32, window, self.atoms.wm_protocols, data);
+
+                xproto::send_event(self.con, false, window, xproto::EVENT_MASK_NO_EVENT, &event);
+            },
+            _ => (),
+        }
+    }
+
+    /// Print a status line describing the tags shown and the urgent tags, if it has changed.
+    ///
+    /// The line has the form `view=1,2;3 urgent=4,nonwork`, where the tags shown on each
+    /// screen are separated by semicolons.
+    fn print_status(&mut self) {
+        fn join<'t, I: Iterator<Item=&'t Tag>>(tags: I) -> String {
+            tags.map(|t| t.to_string()).collect::<Vec<_>>().join(",")
+        }
+
+        let view: Vec<_> = self.hierarchy
+            .screens()
+            .iter()
+            .map(|s| self.hierarchy
+                 .get_tagset(s.tagset())
+                 .map_or_else(String::new, |t| join(t.tags().iter())))
+            .collect();
+        let status = format!("view={} urgent={}",
+                             view.join(";"),
+                             join(self.hierarchy.urgent_tags().iter()));
+
+        if status != self.status {
+            let mut stdout = io::stdout();
+
+            if writeln!(stdout, "{}", status).and_then(|_| stdout.flush()).is_err() {
+                warn!("could not print status");
+            }
+
+            self.status = status;
+        }
+    }
+
+    /// Recompute whether a client is urgent from its `WM_HINTS` and `_NET_WM_STATE`.
+    ///
+    /// Returns `true` if the urgency has changed.
+    fn update_urgency(&mut self, window: xproto::Window) -> bool {
+        let urgent = match self.hierarchy.get_client(&window) {
+            Some(c) => c.properties().hints.is_some_and(|h| h.urgent) ||
+                c.properties().has_state(WindowState::DemandsAttention),
+            None => return false,
+        };
+
+        self.hierarchy.set_urgent(&window, urgent)
+    }
+
+    /// Clear the urgency of a client that has been focused.
+    ///
+    /// `_NET_WM_STATE_DEMANDS_ATTENTION` is removed, while the urgency hint in `WM_HINTS` is
+    /// left to the client.
+    fn clear_urgency(&mut self, window: xproto::Window) {
+        if !self.hierarchy.set_urgent(&window, false) {
+            return;
+        }
+
+        if let Some(client) = self.hierarchy.get_client_mut(&window) {
+            let state = &mut client.properties_mut().state;
+
+            if state.contains(&WindowState::DemandsAttention) {
+                state.retain(|s| *s != WindowState::DemandsAttention);
+                self.ewmh.set_wm_state(&self.atoms, window, state);
+            }
+        }
+    }
+
+    /// Focus the client that has most recently become urgent.
+    ///
+    /// If the client is not visible, a tagset containing it is shown on the focused screen.
+    pub fn focus_urgent(&mut self) {
+        let window = match self.hierarchy.most_recent_urgent() {
+            Some(w) => *w,
+            None => return,
+        };
+
+        if self.hierarchy.focus_client(&window) {
+            self.arrange();
+        }
+    }
+
+    /// Determine the desktop representing a set of tags: the one of the first configured tag
+    /// among them.
+    fn desktop_of<'t, I: Iterator<Item=&'t Tag>>(tags: I) -> Option<u32> {
+        tags.filter_map(|t| config::TAGS.iter().position(|c| c == t))
+            .min()
+            .map(|d| d as u32)
+    }
+
+    /// Publish the desktop of a client, if tags are exposed as desktops.
+    fn publish_desktop(&self, window: xproto::Window) {
+        if !config::EWMH_DESKTOPS {
+            return;
+        }
+
+        if let Some(client) = self.hierarchy.get_client(&window) {
+            let all = config::TAGS.iter().all(|t| client.tags().contains(t));
+            let desktop = if all {
+                Some(ewmh::ALL_DESKTOPS)
+            } else {
+                Self::desktop_of(client.tags().iter())
+            };
+
+            if let Some(desktop) = desktop {
+                self.ewmh.set_wm_desktop(&self.atoms, window, desktop);
+            }
+        }
+    }
+
+    /// Show the single tag exposed as the given desktop on the focused screen.
+    fn view_desktop(&mut self, desktop: u32) {
+        if let Some(tag) = config::TAGS.get(desktop as usize) {
+            let tags: BTreeSet<_> = Some(*tag).into_iter().collect();
+            let layout = Self::new_layout(&self.titles);
+
+            if self.hierarchy.view_tags(tags, layout).is_some() {
+                self.arrange();
+            }
+        }
+    }
+
+    /// Retag a client to carry only the tag exposed as the given desktop, or all tags.
+    fn move_to_desktop(&mut self, window: xproto::Window, desktop: u32) {
+        let tags: HashSet<_> = if desktop == ewmh::ALL_DESKTOPS {
+            config::TAGS.iter().cloned().collect()
+        } else if let Some(tag) = config::TAGS.get(desktop as usize) {
+            Some(*tag).into_iter().collect()
+        } else {
+            return;
+        };
+
+        if self.hierarchy.set_client_tags(&window, tags) {
+            self.publish_desktop(window);
+            self.arrange();
+        }
+    }
+
+    /// Ask a client to close, or kill it if it doesn't support `WM_DELETE_WINDOW`.
+    ///
+    /// Clients asked to close are killed if they are still present after the configured
+    /// timeout. Asking a client to close again before that doesn't shorten the timeout.
+    pub fn close_client(&mut self, window: xproto::Window) {
+        let delete = self.hierarchy
+            .get_client(&window)
+            .is_some_and(|c| c.properties().protocols.delete_window);
+
+        if self.pending_kills.contains_key(&window) {
+            debug!("client {} has already been asked to close", window);
+        } else if delete {
+            let data = xproto::ClientMessageData::from_data32([
+                self.atoms.wm_delete_window,
+                xproto::TIME_CURRENT_TIME,
+                0, 0, 0,
+            ]);
+            let event =
+                xproto::ClientMessageEvent::new(32, window, self.atoms.wm_protocols, data);
+
+            xproto::send_event(self.con, false, window, xproto::EVENT_MASK_NO_EVENT, &event);
+
+            if config::CLOSE_TIMEOUT > 0 {
+                let deadline = Instant::now() + Duration::from_millis(config::CLOSE_TIMEOUT);
+                self.pending_kills.insert(window, deadline);
+            }
+        } else {
+            debug!("killing client {}", window);
+            xproto::kill_client(self.con, window);
+        }
+    }
+
+    /// Close all clients in the container under the cursor of the focused tagset.
+    ///
+    /// This is the selected container if any, or the focused client.
+    pub fn close_focused(&mut self) {
+        let clients: Vec<_> = match self.hierarchy
+            .get_focused_tagset()
+            .and_then(|t| self.hierarchy.get_tagset(t))
+        {
+            Some(tagset) => match tagset.tree().get_cursor() {
+                Some(cursor) =>
+                    tagset.tree().subtree_clients(cursor).into_iter().cloned().collect(),
+                None => return,
+            },
+            None => return,
+        };
+
+        for window in clients {
+            self.close_client(window);
+        }
+    }
+
+    /// Kill the clients that have not closed in time after being asked to.
+    fn kill_unresponsive(&mut self) {
+        let now = Instant::now();
+        let expired: Vec<_> = self.pending_kills
+            .iter()
+            .filter(|&(_, deadline)| *deadline <= now)
+            .map(|(w, _)| *w)
+            .collect();
+
+        for window in expired {
+            info!("client {} did not close in time, killing it", window);
+            self.pending_kills.remove(&window);
+            xproto::kill_client(self.con, window);
+        }
+    }
+
+    /// Wait until the X connection or the control socket becomes readable, a control client
+    /// with queued output becomes writable, or the next pending kill is due.
+    fn wait(&self) {
+        let timeout = self.pending_kills
+            .values()
+            .min()
+            .map_or(-1, |d| {
+                let now = Instant::now();
+                let remaining = if *d > now { *d - now } else { Duration::from_secs(0) };
+                (remaining.as_secs() * 1000 + u64::from(remaining.subsec_millis()) + 1)
+                    .min(i32::MAX as u64) as i32
+            });
+
+        let mut fds: Vec<_> = Some(self.con.as_raw_fd())
+            .into_iter()
+            .chain(self.ipc.iter().flat_map(|i| i.fds()))
+            .map(|fd| libc::pollfd { fd, events: libc::POLLIN, revents: 0 })
+            .chain(self.ipc
+                   .iter()
+                   .flat_map(|i| i.pending_fds())
+                   .map(|fd| libc::pollfd { fd, events: libc::POLLOUT, revents: 0 }))
+            .collect();
+
+        unsafe {
+            libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout);
+        }
+    }
+
+    /// Adjust the geometry of a client according to its size hints, if configured to do so.
+    ///
+    /// Fullscreen clients are left alone.
+    fn constrain(client: &Client<xproto::Window>, geometry: &Geometry) -> Geometry {
+        let properties = client.properties();
+        let class = properties.class.as_ref().map(|c| c.class.as_str());
+
+        match properties.size_hints {
+            Some(ref hints) if client.fullscreen().is_none() &&
+                config::SIZE_HINTS.honored_for(class) =>
+                hints.constrain(geometry),
+            _ => *geometry,
+        }
+    }
+
+    /// Make a client fullscreen in the given way, or leave fullscreen.
+    ///
+    /// The client's `_NET_WM_STATE` is updated accordingly. Since fullscreen clients keep
+    /// their position in the tag trees, leaving fullscreen restores their previous placement.
+    pub fn set_fullscreen(&mut self, window: xproto::Window, mode: Option<FullscreenMode>) {
+        let client = match self.hierarchy.get_client_mut(&window) {
+            Some(c) => c,
+            None => return,
+        };
+
+        client.set_fullscreen(mode);
+
+        let state = &mut client.properties_mut().state;
+        state.retain(|s| *s != WindowState::Fullscreen);
+
+        if mode.is_some() {
+            state.push(WindowState::Fullscreen);
+        }
+
+        self.ewmh.set_wm_state(&self.atoms, window, state);
+        self.arrange();
+    }
+
+    /// Toggle the given fullscreen mode of the focused client.
+    ///
+    /// A client fullscreen in another mode is switched to the given one.
+    pub fn toggle_fullscreen(&mut self, mode: FullscreenMode) {
+        let window = match self.hierarchy.get_focused_client() {
+            Some(w) => *w,
+            None => return,
+        };
+
+        let current = self.hierarchy.get_client(&window).and_then(|c| c.fullscreen());
+        let mode = if current == Some(mode) { None } else { Some(mode) };
+
+        self.set_fullscreen(window, mode);
+    }
+
+    /// Toggle the visibility of a scratchpad on the focused screen.
+    pub fn toggle_scratchpad(&mut self, name: &str) {
+        let shown = self.hierarchy.toggle_scratchpad(name);
+        debug!("toggled scratchpad {}: showing {:?}", name, shown);
+
+        self.arrange();
+    }
+
+    /// Handle a button press, focusing the child whose title has been clicked, if any.
+    fn handle_button_press(&mut self, event: &xproto::ButtonPressEvent) {
+        let (tagset, container) =
+            match self.titles.handle_click(event.event(), event.event_x(), event.event_y()) {
+                Some(c) => c,
+                None => return,
+            };
+
+        if let Some(tagset) = self.hierarchy.get_tagset_mut(tagset) {
+            if tagset.tree_mut().focus_container(container).is_some() {
+                self.arrange();
+            }
+        }
+    }
+
+    /// Handle the pointer entering a client, focusing it if focus follows the mouse.
+    fn handle_enter_notify(&mut self, event: &xproto::EnterNotifyEvent) {
+        if event.mode() != xproto::NOTIFY_MODE_NORMAL as u8 ||
+            event.detail() == xproto::NOTIFY_DETAIL_INFERIOR as u8
+        {
+            return;
+        }
+
+        // the pointer didn't move, the windows below it did
+        let sequence = unsafe { (*event.ptr).sequence };
+        if (sequence.wrapping_sub(self.enter_marker) as i16) < 0 {
+            return;
+        }
+
+        let visible = self.hierarchy
+            .get_client(&event.event())
+            .is_some_and(|c| c.is_mapped());
+
+        if visible && self.input_focus != Some(event.event()) &&
+            self.hierarchy.focus_client(&event.event())
+        {
+            self.arrange();
+        }
+    }
+
+    /// Handle a property change, updating the cached properties of the client.
+    fn handle_property_notify(&mut self, event: &xproto::PropertyNotifyEvent) {
+        if let Some(properties) = self.docks.get_mut(&event.window()) {
+            let before = properties.reserved();
+            properties.update(self.con, &self.atoms, event.window(), event.atom());
+
+            if properties.reserved() != before {
+                self.update_reserved();
+                self.arrange();
+            }
+
+            return;
+        }
+
+        let property = match self.hierarchy.get_client_mut(&event.window()) {
+            Some(client) => client
+                .properties_mut()
+                .update(self.con, &self.atoms, event.window(), event.atom()),
+            None => return,
+        };
+
+        debug!("property of window {} changed: {:?}", event.window(), property);
+
+        match property {
+            Some(Property::WmName) | Some(Property::NetWmName) => self.arrange(),
+            // size constraints apply to the geometries clients are arranged with
+            Some(Property::WmNormalHints) => self.arrange(),
+            Some(Property::WmHints) | Some(Property::NetWmState)
+                if self.update_urgency(event.window()) => self.arrange(),
+            _ => (),
+        }
+    }
+
+    /// Handle a client message, mapping EWMH requests onto the client hierarchy.
+    fn handle_client_message(&mut self, event: &xproto::ClientMessageEvent) {
+        let message = match ClientMessage::decode(&self.atoms, event) {
+            Some(m) => m,
+            None => return,
+        };
+
+        debug!("client message: {:?}", message);
+
+        match message {
+            ClientMessage::ActiveWindow(window) => {
+                if self.hierarchy.focus_client(&window) {
+                    self.arrange();
+                }
+            },
+            ClientMessage::CloseWindow(window) => {
+                if self.hierarchy.get_client(&window).is_some() {
+                    self.close_client(window);
+                }
+            },
+            ClientMessage::WmState(window, action, first, second) => {
+                let client = match self.hierarchy.get_client_mut(&window) {
+                    Some(client) => client,
+                    None => return,
+                };
+
+                let mut changed = action.apply(&mut client.properties_mut().state, first);
+
+                if let Some(second) = second {
+                    changed |= action.apply(&mut client.properties_mut().state, second);
+                }
+
+                if !changed {
+                    return;
+                }
+
+                self.ewmh.set_wm_state(&self.atoms, window, &client.properties().state);
+
+                let requested = client.properties().has_state(WindowState::Fullscreen);
+                let mut rearrange = requested != client.fullscreen().is_some();
+
+                if rearrange {
+                    let mode = if requested { Some(FullscreenMode::Screen) } else { None };
+                    client.set_fullscreen(mode);
+                }
+
+                rearrange |= self.update_urgency(window);
+
+                if rearrange {
+                    self.arrange();
+                }
+            },
+            ClientMessage::MoveResizeWindow(window, request) => {
+                self.move_resize(window, request);
+            },
+            ClientMessage::CurrentDesktop(desktop) if config::EWMH_DESKTOPS => {
+                self.view_desktop(desktop);
+            },
+            ClientMessage::WmDesktop(window, desktop) if config::EWMH_DESKTOPS => {
+                self.move_to_desktop(window, desktop);
+            },
+            ClientMessage::CurrentDesktop(_) | ClientMessage::WmDesktop(..) => (),
+        }
+    }
+
+    /// Move and/or resize a window as requested by a client.
+    ///
+    /// Managed clients can only be moved and resized while floating, unmanaged windows are
+    /// configured directly.
+    fn move_resize(&mut self, window: xproto::Window, request: MoveResize) {
+        if self.hierarchy.get_client(&window).is_none() {
+            let values: Vec<_> = [
+                (xproto::CONFIG_WINDOW_X, request.x.map(|x| x as u32)),
+                (xproto::CONFIG_WINDOW_Y, request.y.map(|y| y as u32)),
+                (xproto::CONFIG_WINDOW_WIDTH, request.width),
+                (xproto::CONFIG_WINDOW_HEIGHT, request.height),
+            ]
+                .iter()
+                .filter_map(|&(m, v)| v.map(|v| (m as u16, v)))
+                .collect();
+
+            xproto::configure_window(self.con, window, &values);
+            return;
+        }
+
+        let current = match xproto::get_geometry(self.con, window).get_reply() {
+            Ok(r) => r,
+            Err(_) => return,
+        };
+
+        let geometry = Geometry::new(
+            request.x.unwrap_or_else(|| i32::from(current.x())).max(0) as u32,
+            request.y.unwrap_or_else(|| i32::from(current.y())).max(0) as u32,
+            request.width.unwrap_or_else(|| u32::from(current.width())),
+            request.height.unwrap_or_else(|| u32::from(current.height())));
+
+        if self.hierarchy.set_floating_geometry(&window, geometry) {
+            self.arrange();
+        }
+    }
+
+    /// Handle a configure request.
+    ///
+    /// Unmanaged windows are configured as requested, managed ones keep the geometry assigned
+    /// by the layout, and are told so with a synthetic `ConfigureNotify` event, as required
+    /// by ICCCM for requests that are not honored.
+    fn handle_configure_request(&mut self, event: &xproto::ConfigureRequestEvent) {
+        if self.hierarchy.get_client(&event.window()).is_some() {
+            self.arrange();
+            self.notify_geometry(event.window());
+            return;
+        }
+
+        let mask = event.value_mask();
+        let values = [
+            (xproto::CONFIG_WINDOW_X, event.x() as u32),
+            (xproto::CONFIG_WINDOW_Y, event.y() as u32),
+            (xproto::CONFIG_WINDOW_WIDTH, u32::from(event.width())),
+            (xproto::CONFIG_WINDOW_HEIGHT, u32::from(event.height())),
+            (xproto::CONFIG_WINDOW_BORDER_WIDTH, u32::from(event.border_width())),
+            (xproto::CONFIG_WINDOW_SIBLING, event.sibling()),
+            (xproto::CONFIG_WINDOW_STACK_MODE, u32::from(event.stack_mode())),
+        ];
+        let values: Vec<_> = values
+            .iter()
+            .filter(|&&(m, _)| mask & m as u16 != 0)
+            .map(|&(m, v)| (m as u16, v))
+            .collect();
+
+        xproto::configure_window(self.con, event.window(), &values);
+    }
+
+    /// Send a synthetic `ConfigureNotify` event describing its current geometry to a client.
+    fn notify_geometry(&self, window: xproto::Window) {
+        let geometry = match xproto::get_geometry(self.con, window).get_reply() {
+            Ok(reply) => reply,
+            Err(_) => return,
+        };
+
+        let event = xproto::ConfigureNotifyEvent::new(window,
+                                                      window,
+                                                      NONE,
+                                                      geometry.x(),
+                                                      geometry.y(),
+                                                      geometry.width(),
+                                                      geometry.height(),
+                                                      geometry.border_width(),
+                                                      false);
+
+        xproto::send_event(self.con, false, window, xproto::EVENT_MASK_STRUCTURE_NOTIFY, &event);
+    }
+
+    /// Handle a request received on the control socket.
+    fn handle_request(&mut self, request: Request) -> Response {
+        match self.dispatch(request) {
+            Ok(reply) => Response::ok(reply),
+            Err(response) => response,
+        }
+    }
+
+    /// Carry out a request, returning the data queried or the error response.
+    fn dispatch(&mut self, request: Request) -> Result<Option<Reply>, Response> {
+        match request {
+            Request::Focus { direction: Some(direction), criteria: None } =>
+                self.focus_direction(direction),
+            Request::Focus { direction: None, criteria: Some(criteria) } => {
+                let window = self.select(&criteria)?[0];
+                self.focus_window(window)
+            },
+            Request::Focus { .. } => Err(Response::error(
+                ErrorKind::InvalidRequest, "focus requires either a direction or criteria")),
+            Request::FocusUrgent => if self.hierarchy.most_recent_urgent().is_some() {
+                self.focus_urgent();
+                Ok(None)
+            } else {
+                Err(Response::error(ErrorKind::NotFound, "no client is urgent"))
+            },
+            Request::FocusWindow { window } => if self.hierarchy.get_client(&window).is_some() {
+                self.focus_window(window)
+            } else {
+                Err(Response::error(ErrorKind::NotFound, "no such client"))
+            },
+            Request::FocusPrevious { global } => if self.hierarchy.focus_previous(global) {
+                self.arrange();
+                Ok(None)
+            } else {
+                Err(Response::error(ErrorKind::NotFound, "no client has been focused before"))
+            },
+            Request::Cycle { reverse, global } => if self.hierarchy.cycle_focus(global, !reverse) {
+                self.arrange();
+                Ok(None)
+            } else {
+                Err(Response::error(ErrorKind::NotFound, "no clients to cycle through"))
+            },
+            Request::EndCycle => {
+                self.hierarchy.end_cycle();
+                Ok(None)
+            },
+            Request::Jump { query } => {
+                let search = Search::parse(&query)
+                    .map_err(|e| Response::error(ErrorKind::InvalidArgument, e))?;
+
+                match search.find(&self.hierarchy).cloned() {
+                    Some(window) => self.focus_window(window),
+                    None => Err(Response::error(ErrorKind::NotFound,
+                                                format!("no client matches {}", query))),
+                }
+            },
+            Request::FocusScreen { screen } => if self.hierarchy.focus_screen(screen) {
+                self.arrange();
+                Ok(None)
+            } else {
+                Err(Response::error(ErrorKind::NotFound, "no such screen"))
+            },
+            Request::FocusTagset { tagset } => {
+                let found = self.hierarchy
+                    .find_tagset(tagset)
+                    .is_some_and(|t| self.hierarchy.focus_tagset(t));
+
+                if found {
+                    self.arrange();
+                    Ok(None)
+                } else {
+                    Err(Response::error(ErrorKind::NotFound, "no such tagset"))
+                }
+            },
+            Request::FocusMark { name } => if self.hierarchy.focus_mark(&name) {
+                self.arrange();
+                Ok(None)
+            } else {
+                Err(Response::error(ErrorKind::NotFound, format!("no mark named {}", name)))
+            },
+            Request::Move { direction } => self.move_focused(direction, false),
+            Request::Swap { direction } => self.move_focused(direction, true),
+            Request::SwapMark { name } => {
+                let (id, cursor, marked) = self.marked(&name)?;
+                self.move_container(id, cursor, marked, true)
+            },
+            Request::MoveToMark { name } => {
+                let (id, cursor, marked) = self.marked(&name)?;
+                self.move_container(id, cursor, marked, false)
+            },
+            Request::Split { split } => self.split_focused(split),
+            Request::SetSplit { split } => self.change_split(Some(split)),
+            Request::ToggleSplit => self.change_split(None),
+            Request::Mark { name } => {
+                let (id, cursor) = self.cursor()?;
+
+                if self.hierarchy.mark_container(id, cursor, &name) {
+                    Ok(None)
+                } else {
+                    Err(Response::error(ErrorKind::NotFound, "the focused container doesn't exist"))
+                }
+            },
+            Request::Unmark { name } => if self.hierarchy.unmark(&name) {
+                Ok(None)
+            } else {
+                Err(Response::error(ErrorKind::NotFound, format!("no mark named {}", name)))
+            },
+            Request::Float { criteria: None } => self.toggle_floating(),
+            Request::Float { criteria: Some(criteria) } => {
+                for window in self.select(&criteria)? {
+                    self.hierarchy.toggle_client_floating(&window);
+                }
+
+                self.arrange();
+                Ok(None)
+            },
+            Request::Fullscreen { mode } => {
+                self.focused_client()?;
+
+                self.toggle_fullscreen(match mode {
+                    gwm_ipc::Fullscreen::Screen => FullscreenMode::Screen,
+                    gwm_ipc::Fullscreen::Container => FullscreenMode::Container,
+                });
+
+                Ok(None)
+            },
+            Request::Close { criteria: None } => {
+                self.cursor()?;
+                self.close_focused();
+
+                Ok(None)
+            },
+            Request::Close { criteria: Some(criteria) } => {
+                for window in self.select(&criteria)? {
+                    self.close_client(window);
+                }
+
+                Ok(None)
+            },
+            Request::Tag { tags, mode, criteria } => {
+                let windows = self.targets(criteria)?;
+                self.tag_clients(&windows, &tags, mode)
+            },
+            Request::View { tags, mode } => self.view(&tags, mode),
+            Request::Layout { param, value, relative } =>
+                self.send_layout_message(param, value, relative),
+            Request::ToggleScratchpad { name } => {
+                self.scratchpad(&name)?;
+                self.toggle_scratchpad(&name);
+
+                Ok(None)
+            },
+            Request::MoveToScratchpad { name, criteria } => {
+                self.scratchpad(&name)?;
+
+                for window in self.targets(criteria)? {
+                    self.hierarchy.move_to_scratchpad(&name, window);
+                }
+
+                self.arrange();
+                Ok(None)
+            },
+            Request::GetScreens => Ok(Some(Reply::Screens(self.screen_info()))),
+            Request::GetClients => Ok(Some(Reply::Clients(self.client_info()))),
+            Request::GetTagsets => Ok(Some(Reply::Tagsets(self.tagset_info()))),
+            // subscriptions are handled by the connection they are made on
+            Request::Subscribe { .. } => Ok(None),
+        }
+    }
+
+    /// Get the focused tagset and the container under its cursor.
+    fn cursor(&self) -> Result<(TagSetId, ArenaContainerId), Response> {
+        self.hierarchy
+            .get_focused_tagset()
+            .and_then(|t| Some((t, self.hierarchy.get_tagset(t)?.tree().get_cursor()?)))
+            .ok_or_else(|| Response::error(ErrorKind::NoFocus, "no container is focused"))
+    }
+
+    /// Get the focused client.
+    fn focused_client(&self) -> Result<xproto::Window, Response> {
+        self.hierarchy
+            .get_focused_client()
+            .cloned()
+            .ok_or_else(|| Response::error(ErrorKind::NoFocus, "no client is focused"))
+    }
+
+    /// Get the clients matching criteria, in the order they have been managed.
+    ///
+    /// Fails if no client matches.
+    fn select(&self, criteria: &str) -> Result<Vec<xproto::Window>, Response> {
+        let criteria = Criteria::parse(criteria)
+            .map_err(|e| Response::error(ErrorKind::InvalidArgument, e))?;
+
+        let selected: Vec<_> = self.client_list
+            .iter()
+            .filter(|w| self.hierarchy.get_client(w).is_some_and(|c| criteria.matches(c)))
+            .cloned()
+            .collect();
+
+        if selected.is_empty() {
+            Err(Response::error(ErrorKind::NotFound, "no client matches the criteria"))
+        } else {
+            Ok(selected)
+        }
+    }
+
+    /// Get the clients a request acts on: the ones matching its criteria, if given, or the
+    /// focused client.
+    fn targets(&self, criteria: Option<String>) -> Result<Vec<xproto::Window>, Response> {
+        match criteria {
+            Some(criteria) => self.select(&criteria),
+            None => Ok(vec![self.focused_client()?]),
+        }
+    }
+
+    /// Check that a scratchpad exists.
+    fn scratchpad(&self, name: &str) -> Result<(), Response> {
+        if self.hierarchy.has_scratchpad(name) {
+            Ok(())
+        } else {
+            Err(Response::error(ErrorKind::NotFound, format!("no scratchpad named {}", name)))
+        }
+    }
+
+    /// Translate a direction given in a request to a direction in a tag tree.
+    fn tree_direction(direction: gwm_ipc::Direction) -> Direction {
+        match direction {
+            gwm_ipc::Direction::Left => Direction::Left,
+            gwm_ipc::Direction::Up => Direction::Up,
+            gwm_ipc::Direction::Right => Direction::Right,
+            gwm_ipc::Direction::Down => Direction::Down,
+            gwm_ipc::Direction::Next => Direction::PreOrderForward,
+            gwm_ipc::Direction::Prev => Direction::PreOrderBackward,
+        }
+    }
+
+    /// Find the neighbour of the container under the cursor in the given direction.
+    fn neighbour(&self, direction: gwm_ipc::Direction)
+        -> Result<(TagSetId, ArenaContainerId, ArenaContainerId), Response>
+    {
+        let (id, cursor) = self.cursor()?;
+        let tagset = self.hierarchy.get_tagset(id).unwrap();
+
+        let neighbour = tagset.layout().find_container(
+            tagset.tree(), ContainerId::Index(cursor), Self::tree_direction(direction));
+
+        match neighbour {
+            Some(ContainerId::Index(n)) => Ok((id, cursor, n)),
+            _ => Err(Response::error(ErrorKind::NotFound,
+                                     format!("no container {:?} of the focused one",
+                                             direction))),
+        }
+    }
+
+    /// Focus the neighbour of the focused container in the given direction.
+    fn focus_direction(&mut self, direction: gwm_ipc::Direction)
+        -> Result<Option<Reply>, Response>
+    {
+        let (id, _, neighbour) = self.neighbour(direction)?;
+
+        if let Some(tagset) = self.hierarchy.get_tagset_mut(id) {
+            tagset.tree_mut().focus_container(neighbour);
+        }
+
+        self.arrange();
+
+        Ok(None)
+    }
+
+    /// Find the container with a mark, which has to be in the same tagset as the cursor.
+    fn marked(&self, name: &str)
+        -> Result<(TagSetId, ArenaContainerId, ArenaContainerId), Response>
+    {
+        let (id, cursor) = self.cursor()?;
+
+        match self.hierarchy.find_mark(name) {
+            Some((tagset, marked)) if tagset == id => Ok((id, cursor, marked)),
+            Some(_) => Err(Response::error(ErrorKind::Unsupported,
+                                           format!("mark {} is not in the focused tagset",
+                                                   name))),
+            None => Err(Response::error(ErrorKind::NotFound, format!("no mark named {}", name))),
+        }
+    }
+
+    /// Move the focused container past its neighbour in the given direction, or swap the two.
+    fn move_focused(&mut self, direction: gwm_ipc::Direction, swap: bool)
+        -> Result<Option<Reply>, Response>
+    {
+        let (id, cursor, neighbour) = self.neighbour(direction)?;
+        self.move_container(id, cursor, neighbour, swap)
+    }
+
+    /// Move the container under the cursor next to another one in its tagset, or swap the two.
+    fn move_container(&mut self, id: TagSetId, cursor: ArenaContainerId,
+                      target: ArenaContainerId, swap: bool)
+        -> Result<Option<Reply>, Response>
+    {
+        let focused = self.hierarchy.get_focused_client().cloned();
+        let tagset = self.hierarchy.get_tagset_mut(id).unwrap();
+
+        let changed = tagset.with_layout(|layout, tree| if swap {
+            layout.swap_containers(tree, ContainerId::Index(cursor), ContainerId::Index(target))
+        } else {
+            layout.move_container(tree, ContainerId::Index(target), ContainerId::Index(cursor))
+        });
+
+        if !changed {
+            return Err(Response::error(ErrorKind::Unsupported,
+                                       "the layout doesn't allow the container to move"));
+        }
+
+        // the focus follows the client moved
+        if let Some(window) = focused {
+            if let Some(container) = tagset.tree().find_client(&window) {
+                tagset.tree_mut().focus_container(container);
+            }
+        }
+
+        self.arrange();
+
+        Ok(None)
+    }
+
+    /// Wrap the focused container in a new split container.
+    fn split_focused(&mut self, split: gwm_ipc::Split) -> Result<Option<Reply>, Response> {
+        let (id, cursor) = self.cursor()?;
+
+        if let Some(tagset) = self.hierarchy.get_tagset_mut(id) {
+            tagset.tree_mut().split_container(cursor, Self::split_type(split));
+        }
+
+        self.arrange();
+
+        Ok(None)
+    }
+
+    /// Set the split type of the split container holding the focused container, or cycle it
+    /// if no split type is given.
+    ///
+    /// The tree is normalized afterwards, as the split may now have the same orientation as
+    /// its parent.
+    fn change_split(&mut self, split: Option<gwm_ipc::Split>) -> Result<Option<Reply>, Response> {
+        let (id, cursor) = self.cursor()?;
+
+        if let Some(tagset) = self.hierarchy.get_tagset_mut(id) {
+            let tree = tagset.tree_mut();
+            let parent = tree
+                .get_container(cursor)
+                .and_then(|c| c.get_parent())
+                .unwrap_or(ContainerId::Root);
+
+            match split {
+                Some(split) => {
+                    tree.set_split_type(parent, Self::split_type(split));
+                },
+                None => {
+                    tree.toggle_split_type(parent);
+                },
+            }
+
+            tree.normalize();
+        }
+
+        self.arrange();
+
+        Ok(None)
+    }
+
+    /// Get the split type of new split containers of the given kind.
+    fn split_type(split: gwm_ipc::Split) -> SplitType {
+        match split {
+            gwm_ipc::Split::Horizontal => SplitType::Horizontal(SplitRatio::default()),
+            gwm_ipc::Split::Vertical => SplitType::Vertical(SplitRatio::default()),
+            gwm_ipc::Split::Tabbed => SplitType::Tabbed,
+            gwm_ipc::Split::Stacked => SplitType::Stacked,
+        }
+    }
+
+    /// Focus a client, showing a tagset containing it if necessary.
+    fn focus_window(&mut self, window: xproto::Window) -> Result<Option<Reply>, Response> {
+        if self.hierarchy.focus_client(&window) {
+            self.arrange();
+            Ok(None)
+        } else {
+            Err(Response::error(ErrorKind::NotFound, "client is not located in any tagset"))
+        }
+    }
+
+    /// Toggle whether the focused container is floating.
+    fn toggle_floating(&mut self) -> Result<Option<Reply>, Response> {
+        let (id, cursor) = self.cursor()?;
+
+        if let Some(tagset) = self.hierarchy.get_tagset_mut(id) {
+            let floating = tagset.tree().get_container(cursor).is_some_and(|c| c.floating());
+            tagset.tree_mut().set_floating(cursor, !floating);
+        }
+
+        self.arrange();
+
+        Ok(None)
+    }
+
+    /// Look up tags by name and combine them with the current ones.
+    ///
+    /// The result may not be empty.
+    fn combine_tags<'t, I>(current: I, names: &[String], mode: gwm_ipc::TagMode)
+        -> Result<BTreeSet<Tag>, Response>
+        where I: Iterator<Item=&'t Tag>
+    {
+        let given = names
+            .iter()
+            .map(|n| config::TAGS
+                 .iter()
+                 .find(|t| t.to_string() == *n)
+                 .cloned()
+                 .ok_or_else(|| Response::error(ErrorKind::InvalidArgument,
+                                                format!("unknown tag: {}", n))))
+            .collect::<Result<BTreeSet<_>, _>>()?;
+
+        let current: BTreeSet<_> = current.cloned().collect();
+
+        let tags: BTreeSet<_> = match mode {
+            gwm_ipc::TagMode::Set => given,
+            gwm_ipc::TagMode::Add => current.union(&given).cloned().collect(),
+            gwm_ipc::TagMode::Remove => current.difference(&given).cloned().collect(),
+            gwm_ipc::TagMode::Toggle => current.symmetric_difference(&given).cloned().collect(),
+        };
+
+        if tags.is_empty() {
+            Err(Response::error(ErrorKind::InvalidArgument, "the set of tags would be empty"))
+        } else {
+            Ok(tags)
+        }
+    }
+
+    /// Change the tags of clients.
+    ///
+    /// No client is changed if the tags of any of them can't be changed.
+    fn tag_clients(&mut self, windows: &[xproto::Window], names: &[String],
+                   mode: gwm_ipc::TagMode)
+        -> Result<Option<Reply>, Response>
+    {
+        let mut changes = Vec::with_capacity(windows.len());
+
+        for window in windows {
+            let current = self.hierarchy.get_client(window).unwrap().tags();
+            changes.push((*window, Self::combine_tags(current.iter(), names, mode)?));
+        }
+
+        for (window, tags) in changes {
+            self.hierarchy.set_client_tags(&window, tags.into_iter().collect());
+            self.publish_desktop(window);
+        }
+
+        self.arrange();
+
+        Ok(None)
+    }
+
+    /// Change the tags shown on the focused screen.
+    fn view(&mut self, names: &[String], mode: gwm_ipc::TagMode)
+        -> Result<Option<Reply>, Response>
+    {
+        let current = self.hierarchy
+            .get_focused_tagset()
+            .and_then(|t| self.hierarchy.get_tagset(t))
+            .map(|t| t.tags().clone())
+            .unwrap_or_default();
+        let tags = Self::combine_tags(current.iter(), names, mode)?;
+
+        let layout = Self::new_layout(&self.titles);
+        self.hierarchy.view_tags(tags, layout);
+        self.arrange();
+
+        Ok(None)
+    }
+
+    /// Send a message to the layout of the focused tagset.
+    ///
+    /// Fails if the layout doesn't process the message.
+    fn send_layout_message(&mut self, id: usize, value: isize, relative: bool)
+        -> Result<Option<Reply>, Response>
+    {
+        let message = if relative {
+            LayoutMessage::ParamAdd { id, inc: value }
+        } else if value >= 0 {
+            LayoutMessage::ParamAbs { id, value: value as usize }
+        } else {
+            return Err(Response::error(ErrorKind::InvalidArgument,
+                                       "absolute parameter values can't be negative"));
+        };
+
+        let changed = self.hierarchy
+            .get_focused_tagset()
+            .and_then(|t| self.hierarchy.get_tagset_mut(t))
+            .is_some_and(|t| t.with_layout(|layout, _| layout.process_msg(message)));
+
+        if !changed {
+            return Err(Response::error(ErrorKind::Unsupported,
+                                       "the layout doesn't support the message"));
+        }
+
+        self.arrange();
+
+        Ok(None)
+    }
+
+    /// Describe the state of all screens.
+    fn screen_info(&self) -> Vec<gwm_ipc::ScreenInfo> {
+        fn rect(geometry: &Geometry) -> gwm_ipc::Rect {
+            gwm_ipc::Rect {
+                x: geometry.x(),
+                y: geometry.y(),
+                width: geometry.width(),
+                height: geometry.height(),
+            }
+        }
+
+        self.hierarchy
+            .screens()
+            .iter()
+            .enumerate()
+            .map(|(index, screen)| gwm_ipc::ScreenInfo {
+                index,
+                geometry: rect(screen.geometry()),
+                area: rect(screen.area()),
+                tagset: self.hierarchy.get_tagset(screen.tagset()).map_or(0, |t| t.serial()),
+                tags: self.hierarchy
+                    .get_tagset(screen.tagset())
+                    .map_or_else(Vec::new, |t| t.tags().iter().map(|t| t.to_string()).collect()),
+                focused: index == self.hierarchy.get_focused_screen_index(),
+            })
+            .collect()
+    }
+
+    /// Describe the state of all managed clients, in the order they have been managed.
+    fn client_info(&self) -> Vec<gwm_ipc::ClientInfo> {
+        let focused = self.hierarchy.get_focused_client();
+
+        self.client_list
+            .iter()
+            .filter_map(|w| self.hierarchy.get_client(w))
+            .map(|client| {
+                let properties = client.properties();
+                let tags: BTreeSet<_> = client.tags().iter().collect();
+
+                gwm_ipc::ClientInfo {
+                    window: *client.id(),
+                    title: properties.title().to_owned(),
+                    class: properties.class.as_ref().map(|c| c.class.clone()),
+                    instance: properties.class.as_ref().map(|c| c.instance.clone()),
+                    window_type: properties
+                        .window_type
+                        .iter()
+                        .filter_map(|t| t.name())
+                        .map(|t| t.to_owned())
+                        .collect(),
+                    transient_for: properties.transient_for,
+                    pid: properties.pid,
+                    tags: tags.into_iter().map(|t| t.to_string()).collect(),
+                    urgent: client.is_urgent(),
+                    fullscreen: client.fullscreen().map(|f| match f {
+                        FullscreenMode::Screen => gwm_ipc::Fullscreen::Screen,
+                        FullscreenMode::Container => gwm_ipc::Fullscreen::Container,
+                    }),
+                    focused: focused == Some(client.id()),
+                }
+            })
+            .collect()
+    }
+
+    /// Describe the state of all tagsets, in the order they have been created.
+    fn tagset_info(&self) -> Vec<gwm_ipc::TagsetInfo> {
+        fn node(tree: &TagTree<xproto::Window>, id: ContainerId, floating: bool)
+            -> gwm_ipc::Node
+        {
+            let mark = match id {
+                ContainerId::Index(i) => tree.get_container(i).and_then(|c| c.mark()),
+                ContainerId::Root => None,
+            }.map(str::to_owned);
+
+            if let Some(split_type) = tree.get_split_type(id) {
+                gwm_ipc::Node::Split {
+                    split: match split_type {
+                        SplitType::Horizontal(_) => gwm_ipc::Split::Horizontal,
+                        SplitType::Vertical(_) => gwm_ipc::Split::Vertical,
+                        SplitType::Tabbed => gwm_ipc::Split::Tabbed,
+                        SplitType::Stacked => gwm_ipc::Split::Stacked,
+                    },
+                    floating,
+                    mark,
+                    children: tree
+                        .children(id)
+                        .map(|(i, c)| node(tree, ContainerId::Index(i), c.floating()))
+                        .collect(),
+                }
+            } else {
+                gwm_ipc::Node::Client {
+                    window: match id {
+                        ContainerId::Index(i) => tree.get_client(i).cloned().unwrap_or(NONE),
+                        ContainerId::Root => NONE,
+                    },
+                    floating,
+                    mark,
+                }
+            }
+        }
+
+        let screens = self.hierarchy.screens();
+        let mut tagsets: Vec<_> = self.hierarchy.tagsets().collect();
+        tagsets.sort_by_key(|&(_, t)| t.serial());
+
+        tagsets
+            .into_iter()
+            .map(|(id, tagset)| {
+                let tags: BTreeSet<_> = tagset.tags().iter().collect();
+                let tree = tagset.tree();
+
+                gwm_ipc::TagsetInfo {
+                    id: tagset.serial(),
+                    tags: tags.into_iter().map(|t| t.to_string()).collect(),
+                    layout: tagset.layout().name().to_owned(),
+                    screen: screens.iter().position(|s| s.tagset() == id),
+                    focused: tree.root.get_focused().and_then(|c| tree.get_client(c)).cloned(),
+                    tree: node(tree, ContainerId::Root, false),
+                }
+            })
+            .collect()
+    }
+
+    /// Run the main loop of the window manager.
+    pub fn run(&mut self) -> WmResult<()> {
+        loop {
+            self.con.flush();
+
+            while let Some(event) = self.con.poll_for_event() {
+                self.handle_event(&event);
+            }
+
+            if self.con.has_error().is_err() {
+                return Err(XError::IOError.wrap());
+            }
+
+            self.kill_unresponsive();
+
+            if let Some(mut ipc) = self.ipc.take() {
+                ipc.process(|request| self.handle_request(request));
+                self.ipc = Some(ipc);
+            }
+
+            self.send_events();
+
+            self.con.flush();
+            self.wait();
+        }
+    }
+
+    /// Handle an event received from the X server.
+    fn handle_event(&mut self, event: &GenericEvent) {
+        match event.response_type() & !0x80 {
+            xproto::EXPOSE => {
+                let event = unsafe { cast_event::<xproto::ExposeEvent>(&event) };
+
+                if event.count() == 0 {
+                    self.titles.handle_expose(event.window());
+                }
+            },
+            xproto::BUTTON_PRESS => {
+                debug!("generic event: BUTTON_PRESS");
+                let event = unsafe { cast_event::<xproto::ButtonPressEvent>(&event) };
+
+                self.timestamp = event.time();
+                self.handle_button_press(event);
+            },
+            xproto::ENTER_NOTIFY => {
+                let event = unsafe { cast_event::<xproto::EnterNotifyEvent>(&event) };
+
+                self.timestamp = event.time();
+                self.handle_enter_notify(event);
+            },
+            xproto::MAP_REQUEST => {
+                debug!("generic event: MAP_REQUEST");
+                let event = unsafe { cast_event::<xproto::MapRequestEvent>(&event) };
+
+                self.manage(event.window(), false);
+            },
+            xproto::UNMAP_NOTIFY => {
+                debug!("generic event: UNMAP_NOTIFY");
+                let event = unsafe { cast_event::<xproto::UnmapNotifyEvent>(&event) };
+
+                // only clients that unmap themselves are withdrawn
+                let withdrawn = self.docks.contains_key(&event.window()) ||
+                    self.hierarchy
+                        .get_client(&event.window())
+                        .is_some_and(|c| c.is_mapped());
+
+                if withdrawn {
+                    self.unmanage(event.window());
+                }
+            },
+            xproto::DESTROY_NOTIFY => {
+                debug!("generic event: DESTROY_NOTIFY");
+                let event = unsafe { cast_event::<xproto::DestroyNotifyEvent>(&event) };
+
+                self.unmanage(event.window());
+            },
+            xproto::PROPERTY_NOTIFY => {
+                let event = unsafe { cast_event::<xproto::PropertyNotifyEvent>(&event) };
+
+                self.timestamp = event.time();
+                self.handle_property_notify(event);
+            },
+            xproto::CLIENT_MESSAGE => {
+                let event = unsafe { cast_event::<xproto::ClientMessageEvent>(&event) };
+
+                self.handle_client_message(event);
+            },
+            xproto::CONFIGURE_REQUEST => {
+                debug!("generic event: CONFIGURE_REQUEST");
+                let event = unsafe { cast_event::<xproto::ConfigureRequestEvent>(&event) };
+
+                self.handle_configure_request(event);
+            },
+            t => {
+                debug!("generic event (unknown): {}", t);
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshots_report_focus_and_tagset_changes() {
+        let mut tags = BTreeSet::new();
+        tags.insert(Tag::Work(1));
+
+        let tree = TagTree::new(SplitType::Vertical(SplitRatio::default()));
+        let layout = Box::new(Manual { title_height: 0 });
+
+        let mut hierarchy = ClientHierarchy::new();
+        let tagset = hierarchy.add_tagset(TagSet::new(tags.clone(), tree, layout));
+        hierarchy.add_screen(Screen::new(Geometry::new(0, 0, 100, 100), tagset));
+
+        for window in 1..3 {
+            let client_tags = tags.iter().cloned().collect();
+            let client = Client::new(window, ClientProperties::default(), client_tags);
+
+            hierarchy.add_client(client, &Placement::default());
+        }
+
+        let visible = HashMap::new();
+        let old = Snapshot::new(&hierarchy, &visible);
+
+        assert!(Snapshot::new(&hierarchy, &visible).events_since(&old).is_empty());
+
+        // the changes a `focus` and a `view` request make
+        let focused = if hierarchy.get_focused_client() == Some(&1) { 2 } else { 1 };
+        assert!(hierarchy.focus_client(&focused));
+
+        let snapshot = Snapshot::new(&hierarchy, &visible);
+        assert_eq!(snapshot.events_since(&old),
+                   [Event::Focus { screen: 0, window: Some(focused) }]);
+
+        tags.insert(Tag::Work(2));
+        hierarchy.view_tags(tags, Box::new(Manual { title_height: 0 }));
+
+        let events = Snapshot::new(&hierarchy, &visible).events_since(&snapshot);
+        let tags = vec!["1".to_owned(), "2".to_owned()];
+        assert!(events.contains(&Event::Tagset { screen: 0, tags }));
+    }
+}
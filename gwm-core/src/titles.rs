@@ -0,0 +1,384 @@
+use std::collections::HashMap;
+
+use xcb::base::*;
+use xcb::xproto;
+
+use config::TitleConfig;
+use err::*;
+use layout::{Geometry, TitleAreas};
+use tree::{ArenaContainerId, Container, ContainerId, SplitType, TagSetId, TagTree};
+
+/// A pair of graphics contexts used to draw titles in a given style.
+#[derive(Clone, Copy)]
+struct Style {
+    /// Graphics context filling the background of a title.
+    fill: xproto::Gcontext,
+    /// Graphics context drawing the text of a title.
+    text: xproto::Gcontext,
+}
+
+/// A title displayed in a title strip.
+struct Title {
+    /// The child container the title belongs to.
+    container: ArenaContainerId,
+    /// The text of the title.
+    text: String,
+    /// Whether the child is the one currently visible.
+    focused: bool,
+}
+
+/// A title strip drawn above the children of a tabbed or stacked container.
+struct Strip {
+    /// The tagset the container is located in.
+    tagset: TagSetId,
+    /// The tabbed or stacked container.
+    container: ContainerId,
+    /// The container's split type, determining the arrangement of titles.
+    split_type: SplitType,
+    /// The geometry of the strip.
+    geometry: Geometry,
+    /// The titles of all children of the container, in order.
+    titles: Vec<Title>,
+}
+
+impl Strip {
+    /// Compute the geometry of the title at the given index, relative to the strip.
+    ///
+    /// Tabbed containers have their titles side by side, stacked containers on top of each
+    /// other.
+    fn title_geometry(&self, index: usize) -> Geometry {
+        let num_titles = self.titles.len() as u32;
+        let index = index as u32;
+
+        if self.split_type == SplitType::Stacked {
+            let height = self.geometry.height() / num_titles;
+            Geometry::new(0, index * height, self.geometry.width(), height)
+        } else {
+            let width = self.geometry.width() / num_titles;
+            Geometry::new(index * width, 0, width, self.geometry.height())
+        }
+    }
+
+    /// Find the title at a position relative to the strip.
+    fn title_at(&self, x: u32, y: u32) -> Option<&Title> {
+        let num_titles = self.titles.len() as u32;
+
+        if num_titles == 0 {
+            return None;
+        }
+
+        let index = if self.split_type == SplitType::Stacked {
+            y / (self.geometry.height() / num_titles).max(1)
+        } else {
+            x / (self.geometry.width() / num_titles).max(1)
+        };
+
+        self.titles.get(index as usize)
+    }
+}
+
+/// The title strips drawn for all tabbed and stacked containers currently visible.
+///
+/// Each strip is an override-redirect window placed in the title area reserved by the layout.
+/// Titles are drawn using core text rendering, so that no additional libraries are necessary.
+pub struct TitleStrips<'a> {
+    /// X connection used to communicate.
+    con: &'a Connection,
+    /// Root window.
+    root: xproto::Window,
+    /// The root visual, used for the strip windows.
+    visual: xproto::Visualid,
+    /// The appearance of the title strips.
+    config: TitleConfig,
+    /// The font used to draw titles.
+    font: xproto::Font,
+    /// The distance from the top of a title row to the text baseline.
+    baseline: u32,
+    /// The width of the widest character in the font.
+    char_width: u32,
+    /// The height of a single title row.
+    height: u32,
+    /// The style used for unfocused titles.
+    normal: Style,
+    /// The style used for the focused title.
+    focused: Style,
+    /// All strips displayed, by window.
+    strips: HashMap<xproto::Window, Strip>,
+}
+
+impl<'a> TitleStrips<'a> {
+    /// Open the configured font and set up the graphics contexts used to draw titles.
+    pub fn new(con: &'a Connection, screen_num: i32, config: TitleConfig) -> WmResult<Self> {
+        let setup = con.get_setup();
+        let (root, visual) = if let Some(screen) = setup.roots().nth(screen_num as usize) {
+            (screen.root(), screen.root_visual())
+        } else {
+            return Err(XError::CouldNotAcquireScreen.wrap());
+        };
+
+        let font = con.generate_id();
+        xproto::open_font_checked(con, font, config.font)
+            .request_check()
+            .map_err(|_| XError::CouldNotOpenFont(config.font).wrap())?;
+
+        let info = xproto::query_font(con, font)
+            .get_reply()
+            .map_err(|e| XError::RequestFailed(e).wrap())?;
+
+        let padding = config.padding;
+        let ascent = info.font_ascent().max(0) as u32;
+        let descent = info.font_descent().max(0) as u32;
+        let char_width = info.max_bounds().character_width().max(1) as u32;
+
+        let normal = Self::create_style(con, root, font, config.fg, config.bg);
+        let focused = Self::create_style(con, root, font, config.focused_fg, config.focused_bg);
+
+        Ok(TitleStrips {
+            con,
+            root,
+            visual,
+            config,
+            font,
+            baseline: padding + ascent,
+            char_width,
+            height: ascent + descent + 2 * padding,
+            normal,
+            focused,
+            strips: HashMap::new(),
+        })
+    }
+
+    /// Create the graphics contexts for a title style.
+    fn create_style(con: &Connection, root: xproto::Window, font: xproto::Font, fg: u32, bg: u32)
+        -> Style
+    {
+        let fill = con.generate_id();
+        xproto::create_gc(con, fill, root, &[(xproto::GC_FOREGROUND, bg)]);
+
+        let text = con.generate_id();
+        xproto::create_gc(con, text, root, &[
+            (xproto::GC_FOREGROUND, fg),
+            (xproto::GC_BACKGROUND, bg),
+            (xproto::GC_FONT, font),
+        ]);
+
+        Style { fill, text }
+    }
+
+    /// Get the height of a single title row, to be reserved by the layouts.
+    pub fn title_height(&self) -> u32 {
+        self.height
+    }
+
+    /// Update the strips of a tagset after it has been rendered.
+    ///
+    /// Strips are created for all title areas reserved by the layout, and strips of the
+    /// tagset that are no longer needed are destroyed. The title of each child is computed
+    /// from the client it contains, or from the client last focused below it.
+    pub fn update<C, F>(&mut self,
+                        tagset: TagSetId,
+                        tree: &TagTree<C>,
+                        areas: &TitleAreas,
+                        title: F)
+        where F: Fn(&C) -> String
+    {
+        let mut unused: Vec<_> = self.strips
+            .iter()
+            .filter(|&(_, s)| s.tagset == tagset)
+            .map(|(w, s)| (*w, s.container))
+            .collect();
+
+        for (container, geometry) in areas {
+            let split_type = match tree.get_split_type(*container) {
+                Some(s) => s,
+                None => continue,
+            };
+
+            let visible = Self::visible_child(tree, *container);
+
+            let titles = tree
+                .children(*container)
+                .map(|(id, child)| Title {
+                    container: id,
+                    text: Self::representative_client(tree, id, child)
+                        .map_or_else(String::new, &title),
+                    focused: Some(id) == visible,
+                })
+                .collect();
+
+            let window = if let Some(pos) = unused.iter().position(|&(_, c)| c == *container) {
+                unused.swap_remove(pos).0
+            } else {
+                self.create_window()
+            };
+
+            xproto::configure_window(self.con, window, &[
+                (xproto::CONFIG_WINDOW_X as u16, geometry.x()),
+                (xproto::CONFIG_WINDOW_Y as u16, geometry.y()),
+                (xproto::CONFIG_WINDOW_WIDTH as u16, geometry.width().max(1)),
+                (xproto::CONFIG_WINDOW_HEIGHT as u16, geometry.height().max(1)),
+                (xproto::CONFIG_WINDOW_STACK_MODE as u16, xproto::STACK_MODE_ABOVE),
+            ]);
+            xproto::map_window(self.con, window);
+
+            self.strips.insert(window, Strip {
+                tagset,
+                container: *container,
+                split_type,
+                geometry: *geometry,
+                titles,
+            });
+
+            self.draw(window);
+        }
+
+        for (window, _) in unused {
+            self.destroy_strip(window);
+        }
+    }
+
+    /// Destroy all strips of tagsets that are no longer displayed.
+    pub fn retain(&mut self, shown: &[TagSetId]) {
+        let windows: Vec<_> = self.strips
+            .iter()
+            .filter(|&(_, s)| !shown.contains(&s.tagset))
+            .map(|(w, _)| *w)
+            .collect();
+
+        for window in windows {
+            self.destroy_strip(window);
+        }
+    }
+
+    /// Redraw a strip after an expose event.
+    ///
+    /// Returns `false` if the window is not a title strip.
+    pub fn handle_expose(&self, window: xproto::Window) -> bool {
+        if self.strips.contains_key(&window) {
+            self.draw(window);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Determine the child container whose title has been clicked.
+    ///
+    /// The position is relative to the window clicked. Returns the tagset and the child
+    /// container, or `None` if the window is not a title strip.
+    pub fn handle_click(&self, window: xproto::Window, x: i16, y: i16)
+        -> Option<(TagSetId, ArenaContainerId)>
+    {
+        let strip = self.strips.get(&window)?;
+
+        strip
+            .title_at(x.max(0) as u32, y.max(0) as u32)
+            .map(|t| (strip.tagset, t.container))
+    }
+
+    /// Determine the child of a container that is currently visible.
+    fn visible_child<C>(tree: &TagTree<C>, container: ContainerId) -> Option<ArenaContainerId> {
+        let last_focused = match container {
+            ContainerId::Root => tree.root.get_focused(),
+            ContainerId::Index(i) => tree.get_container(i).and_then(|c| c.last_focused()),
+        };
+
+        last_focused
+            .and_then(|l| tree.child_containing(container, l))
+            .or_else(|| tree.children(container).next().map(|(id, _)| id))
+    }
+
+    /// Determine the client whose title represents a container.
+    ///
+    /// This is the client itself for client containers, and the client last focused (or the
+    /// first one) below split containers.
+    fn representative_client<'t, C>(tree: &'t TagTree<C>,
+                                    id: ArenaContainerId,
+                                    container: &'t Container<C>) -> Option<&'t C> {
+        let client = match container {
+            Container::Client(_) => id,
+            Container::Split(s) => s.get_last_focused().or_else(|| {
+                tree.preorder(ContainerId::Index(id))
+                    .find(|&(_, c)| c.get_children().is_none())
+                    .map(|(c, _)| c)
+            })?,
+        };
+
+        tree.get_client(client)
+    }
+
+    /// Create a new strip window.
+    fn create_window(&self) -> xproto::Window {
+        let window = self.con.generate_id();
+
+        xproto::create_window(self.con,
+                              COPY_FROM_PARENT as u8,
+                              window,
+                              self.root,
+                              0, 0, 1, 1, 0,
+                              xproto::WINDOW_CLASS_INPUT_OUTPUT as u16,
+                              self.visual,
+                              &[
+                                  (xproto::CW_BACK_PIXEL, self.config.bg),
+                                  (xproto::CW_OVERRIDE_REDIRECT, 1),
+                                  (xproto::CW_EVENT_MASK,
+                                   xproto::EVENT_MASK_EXPOSURE |
+                                   xproto::EVENT_MASK_BUTTON_PRESS),
+                              ]);
+
+        window
+    }
+
+    /// Destroy a strip and its window.
+    fn destroy_strip(&mut self, window: xproto::Window) {
+        self.strips.remove(&window);
+        xproto::destroy_window(self.con, window);
+    }
+
+    /// Draw the titles of a strip.
+    fn draw(&self, window: xproto::Window) {
+        let strip = &self.strips[&window];
+
+        for (index, title) in strip.titles.iter().enumerate() {
+            let geometry = strip.title_geometry(index);
+            let style = if title.focused { self.focused } else { self.normal };
+
+            let rect = xproto::Rectangle::new(geometry.x() as i16,
+                                              geometry.y() as i16,
+                                              geometry.width() as u16,
+                                              geometry.height() as u16);
+            xproto::poly_fill_rectangle(self.con, window, style.fill, &[rect]);
+
+            // core fonts can't render UTF-8, and we can only fit so much text
+            let max_chars = (geometry.width().saturating_sub(2 * self.config.padding) /
+                             self.char_width) as usize;
+            let text: String = title.text
+                .chars()
+                .map(|c| if c.is_ascii() && !c.is_ascii_control() { c } else { '?' })
+                .take(max_chars.min(255))
+                .collect();
+
+            xproto::image_text_8(self.con,
+                                 window,
+                                 style.text,
+                                 (geometry.x() + self.config.padding) as i16,
+                                 (geometry.y() + self.baseline) as i16,
+                                 &text);
+        }
+    }
+}
+
+impl<'a> Drop for TitleStrips<'a> {
+    fn drop(&mut self) {
+        for window in self.strips.keys() {
+            xproto::destroy_window(self.con, *window);
+        }
+
+        for style in &[self.normal, self.focused] {
+            xproto::free_gc(self.con, style.fill);
+            xproto::free_gc(self.con, style.text);
+        }
+
+        xproto::close_font(self.con, self.font);
+    }
+}
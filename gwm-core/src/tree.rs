@@ -3,10 +3,12 @@
 #![allow(dead_code)]
 
 use std::collections::{BTreeSet, HashMap, HashSet};
+use std::hash::Hash;
 use std::ops::{Add, Sub, Mul};
 
 use config::Tag;
 use layout::{Geometry, Layout};
+use properties::ClientProperties;
 
 use generational_arena::Arena;
 pub use generational_arena::Index as ArenaId;
@@ -22,28 +24,971 @@ pub enum ContainerId {
 pub struct Client<C> {
     id: C,
     currently_mapped: bool,
-    properties: (),
+    properties: ClientProperties,
     tags: HashSet<Tag>,
+    /// How the client is fullscreen, if at all.
+    fullscreen: Option<FullscreenMode>,
+    /// When the client has become urgent, if it is, as a sequence number.
+    urgency: Option<u64>,
+}
+
+/// The ways a client can be fullscreen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FullscreenMode {
+    /// The client covers the entire screen, above all other clients.
+    Screen,
+    /// The client covers the container holding it, above its siblings.
+    ///
+    /// This is useful for video players in a tiled split, for instance.
+    Container,
+}
+
+impl<C> Client<C> {
+    /// Create a new, unmapped client.
+    pub fn new(id: C, properties: ClientProperties, tags: HashSet<Tag>) -> Self {
+        Client {
+            id,
+            currently_mapped: false,
+            properties,
+            tags,
+            fullscreen: None,
+            urgency: None,
+        }
+    }
+
+    pub fn id(&self) -> &C {
+        &self.id
+    }
+
+    /// Check whether the window manager currently has the client mapped.
+    pub fn is_mapped(&self) -> bool {
+        self.currently_mapped
+    }
+
+    pub fn set_mapped(&mut self, mapped: bool) {
+        self.currently_mapped = mapped;
+    }
+
+    pub fn properties(&self) -> &ClientProperties {
+        &self.properties
+    }
+
+    pub fn properties_mut(&mut self) -> &mut ClientProperties {
+        &mut self.properties
+    }
+
+    pub fn tags(&self) -> &HashSet<Tag> {
+        &self.tags
+    }
+
+    pub fn fullscreen(&self) -> Option<FullscreenMode> {
+        self.fullscreen
+    }
+
+    pub fn set_fullscreen(&mut self, fullscreen: Option<FullscreenMode>) {
+        self.fullscreen = fullscreen;
+    }
+
+    /// Check whether the client demands the user's attention.
+    pub fn is_urgent(&self) -> bool {
+        self.urgency.is_some()
+    }
+}
+
+/// The placement of a new client in the tag trees it is inserted into.
+#[derive(Clone, Copy, Debug)]
+pub struct Placement {
+    /// Whether the client is floating.
+    pub floating: bool,
+    /// The geometry of the client if floating, centered on the screen if `None`.
+    pub geometry: Option<Geometry>,
+    /// Whether the client is focused.
+    pub focus: bool,
+}
+
+impl Default for Placement {
+    fn default() -> Self {
+        Placement {
+            floating: false,
+            geometry: None,
+            focus: true,
+        }
+    }
 }
 
 pub struct ClientHierarchy<C> {
     screens: Vec<Screen>,
+    /// The index of the focused screen.
+    focused_screen: usize,
     tagsets: Arena<TagSet<C>>,
     clients: HashMap<C, Client<C>>,
+    /// The scratchpads, by name.
+    scratchpads: HashMap<String, Scratchpad<C>>,
+    /// The sequence number assigned to the next client becoming urgent.
+    next_urgency: u64,
+    /// The serial number assigned to the next tagset added.
+    next_tagset_serial: u64,
+    /// All clients, most recently focused first, followed by those never focused in the
+    /// order they have been added.
+    focus_history: Vec<C>,
+    /// The cycle through the focus history in progress, if any.
+    cycle: Option<FocusCycle<C>>,
+}
+
+impl<C> ClientHierarchy<C> {
+    /// Create an empty client hierarchy.
+    pub fn new() -> Self {
+        ClientHierarchy {
+            screens: Vec::new(),
+            focused_screen: 0,
+            tagsets: Arena::new(),
+            clients: HashMap::new(),
+            scratchpads: HashMap::new(),
+            next_urgency: 0,
+            next_tagset_serial: 0,
+            focus_history: Vec::new(),
+            cycle: None,
+        }
+    }
+
+    /// Add a tagset to the hierarchy, assigning it the next serial number, and return its id.
+    pub fn add_tagset(&mut self, mut tagset: TagSet<C>) -> TagSetId {
+        tagset.serial = self.next_tagset_serial;
+        self.next_tagset_serial += 1;
+
+        self.tagsets.insert(tagset)
+    }
+
+    pub fn get_tagset(&self, id: TagSetId) -> Option<&TagSet<C>> {
+        self.tagsets.get(id)
+    }
+
+    pub fn get_tagset_mut(&mut self, id: TagSetId) -> Option<&mut TagSet<C>> {
+        self.tagsets.get_mut(id)
+    }
+
+    /// Iterate over all tagsets, in no particular order.
+    pub fn tagsets(&self) -> impl Iterator<Item=(TagSetId, &TagSet<C>)> {
+        self.tagsets.iter()
+    }
+
+    /// Find a tagset by its serial number.
+    pub fn find_tagset(&self, serial: u64) -> Option<TagSetId> {
+        self.tagsets
+            .iter()
+            .find(|&(_, t)| t.serial == serial)
+            .map(|(id, _)| id)
+    }
+
+    /// Show a tagset, focusing the screen already showing it, if any.
+    ///
+    /// Otherwise, the tagset is shown on the focused screen. Returns `false` if the tagset
+    /// doesn't exist.
+    pub fn focus_tagset(&mut self, id: TagSetId) -> bool {
+        if self.tagsets.get(id).is_none() {
+            return false;
+        }
+
+        if let Some(screen) = self.screens.iter().position(|s| s.tagset == id) {
+            self.focused_screen = screen;
+            return true;
+        }
+
+        match self.screens.get_mut(self.focused_screen) {
+            Some(screen) => {
+                screen.tagset = id;
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Mark a container in a tagset, removing the mark from any other container first.
+    ///
+    /// Returns `false` if the tagset or container doesn't exist.
+    pub fn mark_container(&mut self, tagset: TagSetId, id: ArenaContainerId, mark: &str) -> bool {
+        match self.tagsets.get(tagset) {
+            Some(t) if t.tree.get_container(id).is_some() => (),
+            _ => return false,
+        }
+
+        self.unmark(mark);
+        self.tagsets[tagset].tree.set_mark(id, Some(mark.to_owned()));
+
+        true
+    }
+
+    /// Remove a mark, returning `false` if no container has it.
+    pub fn unmark(&mut self, mark: &str) -> bool {
+        match self.find_mark(mark) {
+            Some((tagset, id)) => {
+                self.tagsets[tagset].tree.set_mark(id, None);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Find the tagset and container with the given mark.
+    pub fn find_mark(&self, mark: &str) -> Option<(TagSetId, ArenaContainerId)> {
+        self.tagsets
+            .iter()
+            .filter_map(|(t, tagset)| tagset.tree.find_mark(mark).map(|id| (t, id)))
+            .next()
+    }
+
+    /// Focus the container with the given mark, showing its tagset if necessary.
+    ///
+    /// Returns `false` if no container has the mark.
+    pub fn focus_mark(&mut self, mark: &str) -> bool {
+        let (tagset, id) = match self.find_mark(mark) {
+            Some(m) => m,
+            None => return false,
+        };
+
+        if !self.focus_tagset(tagset) {
+            return false;
+        }
+
+        self.tagsets[tagset].tree.focus_container(id);
+
+        true
+    }
+
+    /// Add a screen to the hierarchy.
+    pub fn add_screen(&mut self, screen: Screen) {
+        self.screens.push(screen);
+    }
+
+    pub fn screens(&self) -> &[Screen] {
+        &self.screens
+    }
+
+    /// Set the part of a screen's geometry available to clients.
+    pub fn set_screen_area(&mut self, screen: usize, area: Geometry) {
+        if let Some(screen) = self.screens.get_mut(screen) {
+            screen.area = area;
+        }
+    }
+
+    /// Get the index of the focused screen.
+    pub fn get_focused_screen_index(&self) -> usize {
+        self.focused_screen
+    }
+
+    /// Get the focused screen, if any screens exist.
+    pub fn get_focused_screen(&self) -> Option<&Screen> {
+        self.screens.get(self.focused_screen)
+    }
+
+    /// Focus a screen by index.
+    ///
+    /// Returns `false` if the screen doesn't exist.
+    pub fn focus_screen(&mut self, screen: usize) -> bool {
+        if screen < self.screens.len() {
+            self.focused_screen = screen;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Get the tagset shown on the focused screen.
+    pub fn get_focused_tagset(&self) -> Option<TagSetId> {
+        self.get_focused_screen().map(|s| s.tagset)
+    }
+
+    /// Get the focused client, that is, the one focused in the focused screen's tagset.
+    pub fn get_focused_client(&self) -> Option<&C> {
+        let tree = &self.tagsets.get(self.get_focused_tagset()?)?.tree;
+
+        tree.get_client(tree.root.get_focused()?)
+    }
+
+    /// Add an empty scratchpad holding clients with the given `WM_CLASS` class names.
+    pub fn add_scratchpad(&mut self, name: &str, classes: Vec<String>) {
+        let scratchpad = Scratchpad {
+            classes,
+            tree: TagTree::new(SplitType::Vertical(SplitRatio::default())),
+            shown: None,
+        };
+
+        self.scratchpads.insert(name.to_owned(), scratchpad);
+    }
+
+    /// Get all clients hidden in scratchpads.
+    pub fn hidden_scratchpad_clients(&self) -> Vec<&C> {
+        self.scratchpads
+            .values()
+            .flat_map(|s| s.tree.preorder(ContainerId::Root).map(move |(id, _)| id)
+                      .filter_map(move |id| s.tree.get_client(id)))
+            .collect()
+    }
+
+    /// Check whether a scratchpad with the given name exists.
+    pub fn has_scratchpad(&self, name: &str) -> bool {
+        self.scratchpads.contains_key(name)
+    }
+
+    /// Find the scratchpad new clients with the given `WM_CLASS` class name are moved to.
+    pub fn scratchpad_for_class(&self, class: &str) -> Option<&str> {
+        self.scratchpads
+            .iter()
+            .find(|&(_, s)| s.classes.iter().any(|c| c == class))
+            .map(|(name, _)| name.as_str())
+    }
+}
+
+impl<C: Clone + Eq + Hash> ClientHierarchy<C> {
+    pub fn get_client(&self, client: &C) -> Option<&Client<C>> {
+        self.clients.get(client)
+    }
+
+    pub fn get_client_mut(&mut self, client: &C) -> Option<&mut Client<C>> {
+        self.clients.get_mut(client)
+    }
+
+    /// Iterate over all managed clients.
+    pub fn clients(&self) -> impl Iterator<Item=&Client<C>> {
+        self.clients.values()
+    }
+
+    /// Add a client to the hierarchy.
+    ///
+    /// The client is inserted into all tagsets sharing at least one tag with it, using their
+    /// respective layouts, and placed there as requested. Returns `false` if the client is
+    /// already managed.
+    pub fn add_client(&mut self, client: Client<C>, placement: &Placement) -> bool {
+        if self.clients.contains_key(&client.id) {
+            return false;
+        }
+
+        for (_, tagset) in &mut self.tagsets {
+            if tagset.tags.iter().any(|t| client.tags.contains(t)) {
+                tagset.layout.insert_client(&mut tagset.tree, client.id.clone());
+
+                if let Some(id) = tagset.tree.find_client(&client.id) {
+                    if placement.floating {
+                        tagset.tree.set_floating(id, true);
+                        tagset.tree.set_floating_geometry(id, placement.geometry);
+                    }
+
+                    if placement.focus {
+                        tagset.tree.focus_container(id);
+                    }
+                }
+            }
+        }
+
+        self.focus_history.push(client.id.clone());
+        self.clients.insert(client.id.clone(), client);
+
+        true
+    }
+
+    /// Focus a client, showing a tagset containing it if necessary.
+    ///
+    /// Tagsets already shown on a screen are preferred, and their screen is focused.
+    /// Otherwise, the first tagset containing the client is shown on the focused screen.
+    /// Returns `false` if the client is not located in any tagset.
+    pub fn focus_client(&mut self, client: &C) -> bool {
+        let shown = self.screens
+            .iter()
+            .position(|s| self.tagsets
+                .get(s.tagset)
+                .is_some_and(|t| t.tree.find_client(client).is_some()));
+
+        let tagset = if let Some(screen) = shown {
+            self.focused_screen = screen;
+            self.screens[screen].tagset
+        } else {
+            let tagset = self.tagsets
+                .iter()
+                .find(|&(_, t)| t.tree.find_client(client).is_some())
+                .map(|(id, _)| id);
+
+            match (tagset, self.screens.get_mut(self.focused_screen)) {
+                (Some(tagset), Some(screen)) => {
+                    screen.tagset = tagset;
+                    tagset
+                },
+                _ => return false,
+            }
+        };
+
+        let tree = &mut self.tagsets[tagset].tree;
+
+        match tree.find_client(client) {
+            Some(id) => tree.focus_container(id).is_some(),
+            None => false,
+        }
+    }
+
+    /// Toggle whether a client is floating in all tagsets it is located in.
+    ///
+    /// The first tagset found holding the client determines the new state, so that the client
+    /// ends up floating either everywhere or nowhere. Returns `false` if the client isn't
+    /// located in any tagset.
+    pub fn toggle_client_floating(&mut self, client: &C) -> bool {
+        let mut floating = None;
+
+        for (_, tagset) in &mut self.tagsets {
+            if let Some(id) = tagset.tree.find_client(client) {
+                let current = tagset.tree.get_container(id).is_some_and(|c| c.floating());
+                let new = *floating.get_or_insert(!current);
+                tagset.tree.set_floating(id, new);
+            }
+        }
+
+        floating.is_some()
+    }
+
+    /// Set the geometry of a client in all tagsets it is floating in.
+    ///
+    /// Returns `false` if the client isn't floating anywhere.
+    pub fn set_floating_geometry(&mut self, client: &C, geometry: Geometry) -> bool {
+        let mut found = false;
+
+        for (_, tagset) in &mut self.tagsets {
+            if let Some(id) = tagset.tree.find_client(client) {
+                if tagset.tree.is_floating(id) {
+                    tagset.tree.set_floating_geometry(id, Some(geometry));
+                    found = true;
+                }
+            }
+        }
+
+        found
+    }
+
+    /// Change the tags of a client.
+    ///
+    /// The client is removed from all tagsets no longer sharing a tag with it, and inserted
+    /// into the ones that newly do. Clients hidden in a scratchpad only have their tags
+    /// updated. Returns `false` if the client is not managed.
+    pub fn set_client_tags(&mut self, client: &C, tags: HashSet<Tag>) -> bool {
+        match self.clients.get_mut(client) {
+            Some(c) => c.tags = tags,
+            None => return false,
+        }
+
+        if self.is_hidden_in_scratchpad(client) {
+            return true;
+        }
+
+        let tags = &self.clients[client].tags;
+
+        for (_, tagset) in &mut self.tagsets {
+            let matches = tagset.tags.iter().any(|t| tags.contains(t));
+
+            match tagset.tree.find_client(client) {
+                Some(id) if !matches => {
+                    tagset.layout.delete_container(&mut tagset.tree, ContainerId::Index(id));
+                    tagset.focus_history.retain(|c| c != client);
+                },
+                None if matches => {
+                    tagset.layout.insert_client(&mut tagset.tree, client.clone());
+                },
+                _ => (),
+            }
+        }
+
+        true
+    }
+
+    /// Show a tagset with exactly the given tags on the focused screen.
+    ///
+    /// If no such tagset exists, it is created using the given layout, and all clients having
+    /// one of the tags are inserted into it, except for those hidden in a scratchpad. Returns
+    /// the tagset shown.
+    pub fn view_tags(&mut self, tags: BTreeSet<Tag>, layout: Box<Layout<C>>) -> Option<TagSetId> {
+        let existing = self.tagsets
+            .iter()
+            .find(|&(_, t)| t.tags == tags)
+            .map(|(id, _)| id);
+
+        let tagset = match existing {
+            Some(id) => id,
+            None => {
+                let mut tagset = TagSet::new(
+                    tags, TagTree::new(SplitType::Vertical(SplitRatio::default())), layout);
+
+                for client in self.clients.values() {
+                    if self.is_hidden_in_scratchpad(&client.id) {
+                        continue;
+                    }
+
+                    if tagset.tags.iter().any(|t| client.tags.contains(t)) {
+                        tagset.layout.insert_client(&mut tagset.tree, client.id.clone());
+                    }
+                }
+
+                self.add_tagset(tagset)
+            },
+        };
+
+        self.screens.get_mut(self.focused_screen)?.tagset = tagset;
+
+        Some(tagset)
+    }
+
+    /// Set whether a client is urgent.
+    ///
+    /// A client already urgent keeps its place in the order clients have become urgent in.
+    /// Returns `true` if the urgency of the client has changed.
+    pub fn set_urgent(&mut self, client: &C, urgent: bool) -> bool {
+        let client = match self.clients.get_mut(client) {
+            Some(c) => c,
+            None => return false,
+        };
+
+        match (client.urgency, urgent) {
+            (None, true) => {
+                client.urgency = Some(self.next_urgency);
+                self.next_urgency += 1;
+            },
+            (Some(_), false) => client.urgency = None,
+            _ => return false,
+        }
+
+        true
+    }
+
+    /// Get all tags carried by at least one urgent client.
+    pub fn urgent_tags(&self) -> BTreeSet<Tag> {
+        self.clients
+            .values()
+            .filter(|c| c.is_urgent())
+            .flat_map(|c| c.tags.iter().cloned())
+            .collect()
+    }
+
+    /// Get the client that has most recently become urgent.
+    pub fn most_recent_urgent(&self) -> Option<&C> {
+        self.clients
+            .values()
+            .filter_map(|c| c.urgency.map(|u| (u, &c.id)))
+            .max_by_key(|&(u, _)| u)
+            .map(|(_, id)| id)
+    }
+
+    /// Record that a client has been focused, making it the most recently focused one, both
+    /// globally and in the focused tagset.
+    ///
+    /// While cycling through the focus history, the client previewed keeps its place, and
+    /// focusing any other client ends the cycle as `end_cycle` does.
+    pub fn record_focus(&mut self, client: &C) {
+        if let Some(cycle) = self.cycle.take() {
+            match cycle.current() {
+                Some(previewed) if previewed == client => {
+                    self.cycle = Some(cycle);
+                    return;
+                },
+                Some(previewed) => self.push_focus(previewed),
+                None => (),
+            }
+        }
+
+        self.push_focus(client);
+    }
+
+    /// Make a client the most recently focused one, globally and in the focused tagset.
+    fn push_focus(&mut self, client: &C) {
+        if let Some(index) = self.focus_history.iter().position(|c| c == client) {
+            let client = self.focus_history.remove(index);
+            self.focus_history.insert(0, client);
+        }
+
+        let focused = self.get_focused_tagset();
+
+        if let Some(tagset) = focused.and_then(|id| self.tagsets.get_mut(id)) {
+            if tagset.tree.find_client(client).is_some() {
+                tagset.focus_history.retain(|c| c != client);
+                tagset.focus_history.insert(0, client.clone());
+            }
+        }
+    }
+
+    /// Get the clients that can be focused, most recently focused first.
+    ///
+    /// If `global`, these are all clients located in any tagset. Otherwise, these are the
+    /// clients in the focused tagset, where those never focused in it follow in tree order.
+    pub fn focus_order(&self, global: bool) -> Vec<C> {
+        if global {
+            return self.focus_history
+                .iter()
+                .filter(|c| self.tagsets.iter().any(|(_, t)| t.tree.find_client(c).is_some()))
+                .cloned()
+                .collect();
+        }
+
+        let tagset = match self.get_focused_tagset().and_then(|id| self.tagsets.get(id)) {
+            Some(t) => t,
+            None => return Vec::new(),
+        };
+
+        let mut order: Vec<_> = tagset.focus_history
+            .iter()
+            .filter(|c| tagset.tree.find_client(c).is_some())
+            .cloned()
+            .collect();
+
+        for (id, _) in tagset.tree.preorder(ContainerId::Root) {
+            if let Some(client) = tagset.tree.get_client(id) {
+                if !order.contains(client) {
+                    order.push(client.clone());
+                }
+            }
+        }
+
+        order
+    }
+
+    /// Focus the client focused before the focused one, ending any cycle in progress.
+    ///
+    /// If `global`, the client may be located in any tagset, which is shown if necessary.
+    /// Otherwise, only clients in the focused tagset are considered. Returns `false` if there
+    /// is no such client.
+    pub fn focus_previous(&mut self, global: bool) -> bool {
+        self.end_cycle();
+
+        let focused = self.get_focused_client().cloned();
+        let previous = self.focus_order(global)
+            .into_iter()
+            .find(|c| Some(c) != focused.as_ref());
+
+        match previous {
+            Some(client) => self.focus_in_order(&client, global),
+            None => false,
+        }
+    }
+
+    /// Focus the next or previous client in the focus history, starting a cycle if none is in
+    /// progress.
+    ///
+    /// The history isn't reordered until the cycle ends, so that repeated calls preview the
+    /// clients in the order they have been focused. The clients considered are chosen as by
+    /// `focus_previous`. Returns `false` if there are no such clients.
+    pub fn cycle_focus(&mut self, global: bool, forward: bool) -> bool {
+        if self.cycle.as_ref().is_none_or(|c| c.global != global) {
+            self.end_cycle();
+
+            let clients = self.focus_order(global);
+
+            if clients.is_empty() {
+                return false;
+            }
+
+            // without a focused client among them, a forward cycle starts at the first client
+            let focused = self.get_focused_client();
+            let index = clients
+                .iter()
+                .position(|c| Some(c) == focused)
+                .unwrap_or(clients.len() - 1);
+
+            self.cycle = Some(FocusCycle { clients, index, global });
+        }
+
+        let client = {
+            let cycle = self.cycle.as_mut().unwrap();
+            let len = cycle.clients.len();
+            let step = if forward { 1 } else { len - 1 };
+
+            cycle.index = (cycle.index + step) % len;
+            cycle.clients[cycle.index].clone()
+        };
+
+        self.focus_in_order(&client, global)
+    }
+
+    /// End the cycle through the focus history in progress, making the client previewed the
+    /// most recently focused one.
+    ///
+    /// Returns `false` if no cycle is in progress.
+    pub fn end_cycle(&mut self) -> bool {
+        match self.cycle.take() {
+            Some(cycle) => {
+                if let Some(client) = cycle.current() {
+                    self.record_focus(client);
+                }
+
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Focus a client taken from the focus order with the given scope.
+    fn focus_in_order(&mut self, client: &C, global: bool) -> bool {
+        if global {
+            return self.focus_client(client);
+        }
+
+        let focused = self.get_focused_tagset();
+
+        match focused.and_then(|id| self.tagsets.get_mut(id)) {
+            Some(tagset) => match tagset.tree.find_client(client) {
+                Some(id) => tagset.tree.focus_container(id).is_some(),
+                None => false,
+            },
+            None => false,
+        }
+    }
+
+    /// Get all clients, most recently focused first.
+    pub fn focus_history(&self) -> &[C] {
+        &self.focus_history
+    }
+
+    /// Remove a client from the hierarchy, including all tag trees and scratchpads.
+    pub fn remove_client(&mut self, client: &C) -> Option<Client<C>> {
+        self.detach_client(client);
+
+        for scratchpad in self.scratchpads.values_mut() {
+            if scratchpad.shown.as_ref() == Some(client) {
+                scratchpad.shown = None;
+            }
+
+            if let Some(id) = scratchpad.tree.find_client(client) {
+                scratchpad.tree.delete_container(ContainerId::Index(id));
+            }
+        }
+
+        self.focus_history.retain(|c| c != client);
+
+        if self.cycle.as_ref().is_some_and(|c| c.clients.contains(client)) {
+            self.cycle = None;
+        }
+
+        self.clients.remove(client)
+    }
+
+    /// Remove a client from all tagsets' tag trees, using their respective layouts.
+    ///
+    /// Returns `true` if the client has been found in any tag tree.
+    pub fn detach_client(&mut self, client: &C) -> bool {
+        let mut found = false;
+
+        for (_, tagset) in &mut self.tagsets {
+            if let Some(id) = tagset.tree.find_client(client) {
+                tagset.layout.delete_container(&mut tagset.tree, ContainerId::Index(id));
+                found = true;
+            }
+
+            tagset.focus_history.retain(|c| c != client);
+        }
+
+        found
+    }
+
+    /// Move a client to a scratchpad, hiding it.
+    ///
+    /// The client is removed from all tag trees and becomes the most recent client of the
+    /// scratchpad. Returns `false` if no such scratchpad exists.
+    pub fn move_to_scratchpad(&mut self, name: &str, client: C) -> bool {
+        if !self.scratchpads.contains_key(name) {
+            return false;
+        }
+
+        self.detach_client(&client);
+
+        let scratchpad = self.scratchpads.get_mut(name).unwrap();
+
+        if scratchpad.shown.as_ref() == Some(&client) {
+            scratchpad.shown = None;
+        }
+
+        scratchpad.push(client);
+
+        true
+    }
+
+    /// Toggle the visibility of a scratchpad.
+    ///
+    /// If the scratchpad is hidden, its most recent client is shown floating in the focused
+    /// screen's tagset and focused. If it is shown on another tagset, the client is moved to
+    /// the focused one. Otherwise, the client shown is hidden again. Returns the client that
+    /// has been shown, or `None` if it has been hidden or no client was available.
+    pub fn toggle_scratchpad(&mut self, name: &str) -> Option<C> {
+        let target = self.get_focused_tagset()?;
+
+        let shown = self.scratchpads.get(name)?.shown.clone();
+
+        if let Some(client) = shown {
+            let visible = self.tagsets
+                .get(target)
+                .is_some_and(|t| t.tree.find_client(&client).is_some());
+
+            // hide the client if it is visible in the focused tagset, move it there otherwise
+            if visible {
+                self.move_to_scratchpad(name, client);
+                return None;
+            } else {
+                self.detach_client(&client);
+                self.show_floating(target, client.clone());
+                return Some(client);
+            }
+        }
+
+        let client = self.scratchpads.get_mut(name)?.pop()?;
+
+        self.scratchpads.get_mut(name).unwrap().shown = Some(client.clone());
+        self.show_floating(target, client.clone());
+
+        Some(client)
+    }
+
+    /// Check whether a client is hidden in a scratchpad.
+    fn is_hidden_in_scratchpad(&self, client: &C) -> bool {
+        self.scratchpads.values().any(|s| s.tree.find_client(client).is_some())
+    }
+
+    /// Insert a client into a tagset's tag tree as a focused floating container.
+    fn show_floating(&mut self, target: TagSetId, client: C) {
+        if let Some(tagset) = self.tagsets.get_mut(target) {
+            tagset.layout.insert_client(&mut tagset.tree, client.clone());
+
+            if let Some(id) = tagset.tree.find_client(&client) {
+                tagset.tree.set_floating(id, true);
+                tagset.tree.focus_container(id);
+            }
+        }
+    }
+}
+
+/// A scratchpad, holding hidden clients that can be shown on demand.
+///
+/// The clients are kept in a tag tree that is never displayed, ordered from least to most
+/// recently hidden. At most one client of each scratchpad is shown at a time.
+struct Scratchpad<C> {
+    /// The `WM_CLASS` class names of the clients moved to the scratchpad when they appear.
+    classes: Vec<String>,
+    /// The hidden clients of the scratchpad.
+    tree: TagTree<C>,
+    /// The client currently shown, if any.
+    shown: Option<C>,
+}
+
+impl<C: PartialEq> Scratchpad<C> {
+    /// Add a hidden client as the most recent one.
+    fn push(&mut self, client: C) {
+        if let Some((_, last)) = self.tree.root.get_children() {
+            self.tree.insert_client_after(last, client);
+        } else {
+            self.tree.insert_first_client(client);
+        }
+    }
+
+    /// Remove the most recent hidden client.
+    fn pop(&mut self) -> Option<C> {
+        let (_, last) = self.tree.root.get_children()?;
+
+        self.tree.take_client(last)
+    }
+}
+
+/// A cycle through the focus history, previewing clients without reordering the history.
+struct FocusCycle<C> {
+    /// The clients cycled through, in the order of the history when the cycle started.
+    clients: Vec<C>,
+    /// The index of the client previewed.
+    index: usize,
+    /// Whether the clients of all tagsets are cycled through.
+    global: bool,
+}
+
+impl<C> FocusCycle<C> {
+    /// Get the client previewed.
+    fn current(&self) -> Option<&C> {
+        self.clients.get(self.index)
+    }
 }
 
 pub type TagSetId = ArenaId;
 
 pub struct Screen {
     geometry: Geometry,
+    /// The part of the geometry not reserved by docks.
+    area: Geometry,
     tagset: TagSetId,
 }
 
+impl Screen {
+    /// Create a screen with the given geometry, showing the given tagset.
+    pub fn new(geometry: Geometry, tagset: TagSetId) -> Self {
+        Screen {
+            geometry,
+            area: geometry,
+            tagset,
+        }
+    }
+
+    pub fn geometry(&self) -> &Geometry {
+        &self.geometry
+    }
+
+    /// Get the part of the screen's geometry available to clients, excluding docks.
+    pub fn area(&self) -> &Geometry {
+        &self.area
+    }
+
+    pub fn tagset(&self) -> TagSetId {
+        self.tagset
+    }
+}
+
 #[derive(Debug)]
 pub struct TagSet<C> {
+    /// The serial number of the tagset, unique among all tagsets ever added to a hierarchy.
+    serial: u64,
     tags: BTreeSet<Tag>,
     tree: TagTree<C>,
     layout: Box<Layout<C>>,
+    /// The clients focused in the tagset, most recently focused first.
+    focus_history: Vec<C>,
+}
+
+impl<C> TagSet<C> {
+    /// Create a tagset showing a tag tree using the given layout.
+    pub fn new(tags: BTreeSet<Tag>, tree: TagTree<C>, layout: Box<Layout<C>>) -> Self {
+        TagSet {
+            serial: 0,
+            tags,
+            tree,
+            layout,
+            focus_history: Vec::new(),
+        }
+    }
+
+    /// Get the serial number of the tagset, which is never reused for another one.
+    pub fn serial(&self) -> u64 {
+        self.serial
+    }
+
+    pub fn tags(&self) -> &BTreeSet<Tag> {
+        &self.tags
+    }
+
+    pub fn tree(&self) -> &TagTree<C> {
+        &self.tree
+    }
+
+    pub fn tree_mut(&mut self) -> &mut TagTree<C> {
+        &mut self.tree
+    }
+
+    pub fn layout(&self) -> &Layout<C> {
+        &*self.layout
+    }
+
+    /// Modify the tree using the tagset's layout.
+    ///
+    /// Returns the result of the modification, usually whether a new render is necessary.
+    pub fn with_layout<T, F>(&mut self, f: F) -> T
+        where F: FnOnce(&mut Layout<C>, &mut TagTree<C>) -> T
+    {
+        f(&mut *self.layout, &mut self.tree)
+    }
 }
 
 // A tag tree.
@@ -79,6 +1024,121 @@ impl<C> TagTree<C> {
         self.root.selected.or(self.root.focused)
     }
 
+    pub fn get_container(&self, id: ArenaContainerId) -> Option<&Container<C>> {
+        self.containers.get(id)
+    }
+
+    /// Set whether a container is floating.
+    pub fn set_floating(&mut self, id: ArenaContainerId, floating: bool) {
+        if let Some(container) = self.containers.get_mut(id) {
+            container.set_floating(floating);
+        }
+    }
+
+    /// Check whether a container is floating, either by itself or as part of a floating split.
+    pub fn is_floating(&self, id: ArenaContainerId) -> bool {
+        let mut current = id;
+
+        loop {
+            let container = match self.containers.get(current) {
+                Some(c) => c,
+                None => return false,
+            };
+
+            if container.floating() {
+                return true;
+            }
+
+            match container.get_parent() {
+                Some(ContainerId::Index(p)) => current = p,
+                _ => return false,
+            }
+        }
+    }
+
+    /// Set the geometry of a floating client container, or reset it to be centered.
+    pub fn set_floating_geometry(&mut self, id: ArenaContainerId, geometry: Option<Geometry>) {
+        if let Some(Container::Client(c)) = self.containers.get_mut(id) {
+            c.geometry = geometry;
+        }
+    }
+
+    /// Set or remove the mark of a container.
+    pub fn set_mark(&mut self, id: ArenaContainerId, mark: Option<String>) {
+        if let Some(container) = self.containers.get_mut(id) {
+            container.set_mark(mark);
+        }
+    }
+
+    /// Find the container with the given mark.
+    pub fn find_mark(&self, mark: &str) -> Option<ArenaContainerId> {
+        self.preorder(ContainerId::Root)
+            .find(|&(_, c)| c.mark() == Some(mark))
+            .map(|(id, _)| id)
+    }
+
+    /// Get the client stored in a client container.
+    pub fn get_client(&self, id: ArenaContainerId) -> Option<&C> {
+        match self.containers.get(id) {
+            Some(Container::Client(c)) => Some(&c.client),
+            _ => None,
+        }
+    }
+
+    /// Remove a client container from the tree, returning its client.
+    ///
+    /// Returns `None` if the container is not a client container.
+    pub fn take_client(&mut self, id: ArenaContainerId) -> Option<C> {
+        match self.containers.get(id) {
+            Some(Container::Client(_)) => (),
+            _ => return None,
+        }
+
+        let neighbour = self.unlink_container(id);
+        let client = match self.containers.remove(id) {
+            Some(Container::Client(c)) => Some(c.client),
+            _ => None,
+        };
+
+        self.repair_focus(neighbour);
+
+        client
+    }
+
+    /// Focus a container.
+    ///
+    /// If the container is a split container, the client last focused below it is focused,
+    /// or its first client if none has been focused yet. The focus markers of all ancestors
+    /// are updated accordingly, and the selection is cleared. Returns the focused client
+    /// container, if any.
+    pub fn focus_container(&mut self, id: ArenaContainerId) -> Option<ArenaContainerId> {
+        let client = match self.containers.get(id)? {
+            Container::Client(_) => id,
+            Container::Split(s) => s
+                .last_focused
+                .filter(|l| self.containers.contains(*l))
+                .or_else(|| self
+                    .preorder(ContainerId::Index(id))
+                    .find(|(_, c)| c.get_children().is_none())
+                    .map(|(c, _)| c))?,
+        };
+
+        self.root.focused = Some(client);
+        self.root.selected = None;
+
+        let mut current = client;
+
+        while let Some(ContainerId::Index(parent)) = self.containers[current].get_parent() {
+            if let Container::Split(ref mut s) = self.containers[parent] {
+                s.last_focused = Some(client);
+            }
+
+            current = parent;
+        }
+
+        Some(client)
+    }
+
     pub fn insert_first_client(&mut self, client: C) -> ArenaContainerId {
         assert!(self.root.children.is_none());
 
@@ -90,7 +1150,7 @@ impl<C> TagTree<C> {
         id
     }
 
-    /// Insert a client as a sibling before the cursor.
+    /// Insert a client as a sibling before the cursor, with the same weight as the cursor.
     ///
     /// Returns the inserted container. Panics if the cursor is orphaned.
     pub fn insert_client_before(&mut self, cursor: ArenaContainerId, client: C)
@@ -99,13 +1159,15 @@ impl<C> TagTree<C> {
         let parent = self.containers[cursor].get_parent().expect("cursor is orphaned");
         let mut container = ClientContainer::new(client, parent);
 
+        container.weight = self.containers[cursor].weight();
         container.next_sibling = Some(cursor);
 
         let id = self.containers.insert(Container::Client(container));
 
+        let prev = self.containers[cursor].get_prev_sibling();
         self.containers[cursor].set_prev_sibling(Some(id));
 
-        if let Some(prev) = self.containers[cursor].get_prev_sibling() {
+        if let Some(prev) = prev {
             self.containers[id].set_prev_sibling(Some(prev));
             self.containers[prev].set_next_sibling(Some(id));
         } else {
@@ -118,7 +1180,7 @@ impl<C> TagTree<C> {
         id
     }
 
-    /// Insert a client as a sibling after the cursor.
+    /// Insert a client as a sibling after the cursor, with the same weight as the cursor.
     ///
     /// Returns the inserted container.
     pub fn insert_client_after(&mut self, cursor: ArenaContainerId, client: C)
@@ -127,13 +1189,15 @@ impl<C> TagTree<C> {
         let parent = self.containers[cursor].get_parent().expect("cursor is orphaned");
         let mut container = ClientContainer::new(client, parent);
 
+        container.weight = self.containers[cursor].weight();
         container.prev_sibling = Some(cursor);
 
         let id = self.containers.insert(Container::Client(container));
 
+        let next = self.containers[cursor].get_next_sibling();
         self.containers[cursor].set_next_sibling(Some(id));
 
-        if let Some(next) = self.containers[cursor].get_next_sibling() {
+        if let Some(next) = next {
             self.containers[id].set_next_sibling(Some(next));
             self.containers[next].set_prev_sibling(Some(id));
         } else {
@@ -148,33 +1212,31 @@ impl<C> TagTree<C> {
 
     /// Move a subtree as a sibling before the cursor.
     ///
-    /// If the subtree is not orphaned, a check is performed whether the cursor is one of its
-    /// descendants. If so, nothing is done and `false` returned. Otherwise, the subtree is
-    /// reparented properly. If it was orphaned, it is just inserted before the cursor. In both
-    /// cases, `true` is returned.
+    /// If the cursor is part of the subtree, nothing is done and `false` returned. Otherwise,
+    /// the subtree is unlinked from its current position if it isn't orphaned, and inserted
+    /// before the cursor, taking over the cursor's weight. Split containers left without
+    /// children are deleted. Returns `true` in that case.
     pub fn move_subtree_before(&mut self, cursor: ArenaContainerId, tree: ArenaContainerId)
         -> bool
     {
-        if cursor == tree {
+        if !self.detach_subtree(cursor, tree) {
             return false;
         }
 
-        for (id, _) in self.preorder(ContainerId::Index(tree)) {
-            if id == cursor {
-                return false;
-            }
-        }
+        let parent = self.containers[cursor].get_parent().expect("cursor is orphaned");
+        let prev = self.containers[cursor].get_prev_sibling();
+        let weight = self.containers[cursor].weight();
 
-        self.containers[cursor].set_prev_sibling(Some(tree));
+        self.containers[tree].set_parent(Some(parent));
+        self.containers[tree].set_weight(weight);
+        self.containers[tree].set_prev_sibling(prev);
         self.containers[tree].set_next_sibling(Some(cursor));
+        self.containers[cursor].set_prev_sibling(Some(tree));
 
-        if let Some(prev) = self.containers[cursor].get_prev_sibling() {
-            self.containers[tree].set_prev_sibling(Some(prev));
+        if let Some(prev) = prev {
             self.containers[prev].set_next_sibling(Some(tree));
         } else {
-            self.containers[tree].set_prev_sibling(None);
-
-            match self.containers[cursor].get_parent().expect("cursor is orphaned") {
+            match parent {
                 ContainerId::Root => self.root.set_first_child(tree),
                 ContainerId::Index(p) => self.containers[p].set_first_child(tree),
             }
@@ -185,33 +1247,31 @@ impl<C> TagTree<C> {
 
     /// Move a subtree as a sibling after the cursor.
     ///
-    /// If the subtree is not orphaned, a check is performed whether the cursor is one of its
-    /// descendants. If so, nothing is done and `false` returned. Otherwise, the subtree is
-    /// reparented properly. If it was orphaned, it is just inserted after the cursor. In both
-    /// cases, `true` is returned.
+    /// If the cursor is part of the subtree, nothing is done and `false` returned. Otherwise,
+    /// the subtree is unlinked from its current position if it isn't orphaned, and inserted
+    /// after the cursor, taking over the cursor's weight. Split containers left without
+    /// children are deleted. Returns `true` in that case.
     pub fn move_subtree_after(&mut self, cursor: ArenaContainerId, tree: ArenaContainerId)
         -> bool
     {
-        if cursor == tree {
+        if !self.detach_subtree(cursor, tree) {
             return false;
         }
 
-        for (id, _) in self.preorder(ContainerId::Index(tree)) {
-            if id == cursor {
-                return false;
-            }
-        }
+        let parent = self.containers[cursor].get_parent().expect("cursor is orphaned");
+        let next = self.containers[cursor].get_next_sibling();
+        let weight = self.containers[cursor].weight();
 
-        self.containers[cursor].set_next_sibling(Some(tree));
+        self.containers[tree].set_parent(Some(parent));
+        self.containers[tree].set_weight(weight);
         self.containers[tree].set_prev_sibling(Some(cursor));
+        self.containers[tree].set_next_sibling(next);
+        self.containers[cursor].set_next_sibling(Some(tree));
 
-        if let Some(next) = self.containers[cursor].get_next_sibling() {
-            self.containers[tree].set_next_sibling(Some(next));
+        if let Some(next) = next {
             self.containers[next].set_prev_sibling(Some(tree));
         } else {
-            self.containers[tree].set_next_sibling(None);
-
-            match self.containers[cursor].get_parent().expect("cursor is orphaned") {
+            match parent {
                 ContainerId::Root => self.root.set_last_child(tree),
                 ContainerId::Index(p) => self.containers[p].set_last_child(tree),
             }
@@ -220,6 +1280,79 @@ impl<C> TagTree<C> {
         true
     }
 
+    /// Prepare moving a subtree next to the cursor by unlinking it.
+    ///
+    /// Returns `false` if the cursor is part of the subtree.
+    fn detach_subtree(&mut self, cursor: ArenaContainerId, tree: ArenaContainerId) -> bool {
+        if cursor == tree || self.is_descendant(cursor, tree) {
+            return false;
+        }
+
+        if self.containers[tree].get_parent().is_some() {
+            self.unlink_container(tree);
+        }
+
+        true
+    }
+
+    /// Swap the positions of two subtrees.
+    ///
+    /// Returns `false` if one of the containers is part of the subtree rooted by the other.
+    pub fn swap_containers(&mut self, a: ArenaContainerId, b: ArenaContainerId) -> bool {
+        if a == b || self.is_descendant(a, b) || self.is_descendant(b, a) {
+            return false;
+        }
+
+        // a container that is the only child of its parent swaps places along with it
+        let a = self.outermost_only_child(a);
+        let b = self.outermost_only_child(b);
+
+        let prev = self.containers[a].get_prev_sibling();
+        let next = self.containers[a].get_next_sibling();
+        let weight_a = self.containers[a].weight();
+        let weight_b = self.containers[b].weight();
+
+        let swapped = if next == Some(b) {
+            self.move_subtree_after(b, a)
+        } else if prev == Some(b) {
+            self.move_subtree_before(b, a)
+        } else {
+            self.move_subtree_before(b, a);
+
+            match (next, prev) {
+                (Some(next), _) => self.move_subtree_before(next, b),
+                (None, Some(prev)) => self.move_subtree_after(prev, b),
+                (None, None) => unreachable!("container without siblings swapped"),
+            }
+        };
+
+        // each container takes up the space the other one did
+        self.containers[a].set_weight(weight_b);
+        self.containers[b].set_weight(weight_a);
+
+        swapped
+    }
+
+    /// Find the outermost ancestor of a container that only contains it.
+    fn outermost_only_child(&self, id: ArenaContainerId) -> ArenaContainerId {
+        let mut current = id;
+
+        while let Some(ContainerId::Index(parent)) = self.containers[current].get_parent() {
+            if self.num_children(ContainerId::Index(parent)) > 1 {
+                break;
+            }
+
+            current = parent;
+        }
+
+        current
+    }
+
+    /// Check whether a container is located in the subtree rooted by another container.
+    pub fn is_descendant(&self, id: ArenaContainerId, ancestor: ArenaContainerId) -> bool {
+        self.child_containing(ContainerId::Index(ancestor), id).is_some()
+    }
+
     /// Construct a copy of the foreign subtree in the local arena and insert the subtree before
     /// the cursor.
     ///
@@ -232,41 +1365,128 @@ impl<C> TagTree<C> {
         unimplemented!()
     }
 
-    /// Construct a copy of the foreign subtree in the local arena and insert the subtree after
-    /// the cursor.
+    /// Construct a copy of the foreign subtree in the local arena and insert the subtree after
+    /// the cursor.
+    ///
+    /// Returns `None` if `other == self`, otherwise the container id of the root of the new
+    /// subtree.
+    pub fn insert_foreign_subtree_after(&mut self, cursor: ArenaContainerId,
+                                        other: &Self, subtree: ContainerId)
+        -> Option<ArenaContainerId>
+    {
+        unimplemented!()
+    }
+
+    /// Insert a split container as the parent of the given cursor.
+    ///
+    /// The split container takes over the cursor's weight. Returns the id of the newly inserted
+    /// container.
+    pub fn split_container(&mut self, cursor: ArenaContainerId, dir: SplitType)
+        -> ArenaContainerId
+    {
+        let parent = self.containers[cursor].get_parent().expect("cursor is orphaned");
+        let mut container = SplitContainer::new(dir, (cursor, cursor));
+        container.parent = Some(parent);
+        container.weight = self.containers[cursor].weight();
+        let id = self.containers.insert(Container::Split(container));
+
+        let (split, child) = self.containers.get2_mut(id, cursor);
+        split.unwrap().swap_siblings(child.unwrap());
+        self.containers[cursor].set_parent(Some(ContainerId::Index(id)));
+        self.containers[cursor].set_weight(1);
+
+        if let Some(prev) = self.containers[id].get_prev_sibling() {
+            self.containers[prev].set_next_sibling(Some(id));
+        }
+
+        if let Some(next) = self.containers[id].get_next_sibling() {
+            self.containers[next].set_prev_sibling(Some(id));
+        }
+
+        match parent {
+            ContainerId::Root => self.root.update_children(cursor, id),
+            ContainerId::Index(i) => self.containers[i].update_children(cursor, id),
+        }
+
+        id
+    }
+
+    /// Get the split type of a container, or `None` if it is a client container.
+    pub fn get_split_type(&self, id: ContainerId) -> Option<SplitType> {
+        match id {
+            ContainerId::Root => Some(self.root.split_type),
+            ContainerId::Index(i) => self.containers[i].get_split_type(),
+        }
+    }
+
+    /// Set the split type of a container.
+    ///
+    /// Returns `false` if the container is a client container.
+    pub fn set_split_type(&mut self, id: ContainerId, split_type: SplitType) -> bool {
+        let (current, last) = match id {
+            ContainerId::Root => (&mut self.root.split_type, &mut self.root.last_split_type),
+            ContainerId::Index(i) => match self.containers[i] {
+                Container::Split(ref mut s) => (&mut s.split_type, &mut s.last_split_type),
+                Container::Client(_) => return false,
+            },
+        };
+
+        *current = split_type;
+
+        if !split_type.shows_single_child() {
+            *last = split_type;
+        }
+
+        true
+    }
+
+    /// Cycle the split type of a container from split to tabbed to stacked and back.
     ///
-    /// Returns `None` if `other == self`, otherwise the container id of the root of the new
-    /// subtree.
-    pub fn insert_foreign_subtree_after(&mut self, cursor: ArenaContainerId,
-                                        other: &Self, subtree: ContainerId)
-        -> Option<ArenaContainerId>
-    {
-        unimplemented!()
+    /// The orientation and ratio of the split are remembered while the container is tabbed or
+    /// stacked. Returns the new split type, or `None` if the container is a client container.
+    pub fn toggle_split_type(&mut self, id: ContainerId) -> Option<SplitType> {
+        let (current, last) = match id {
+            ContainerId::Root => (self.root.split_type, self.root.last_split_type),
+            ContainerId::Index(i) => match self.containers[i] {
+                Container::Split(ref s) => (s.split_type, s.last_split_type),
+                Container::Client(_) => return None,
+            },
+        };
+
+        let next = match current {
+            SplitType::Horizontal(_) | SplitType::Vertical(_) => SplitType::Tabbed,
+            SplitType::Tabbed => SplitType::Stacked,
+            SplitType::Stacked => last,
+        };
+
+        self.set_split_type(id, next);
+
+        Some(next)
     }
 
-    /// Insert a split container as the parent of the given cursor.
+    /// Find the child of a container that contains the given descendant.
     ///
-    /// Returns the id of the newly inserted container.
-    pub fn split_container(&mut self, cursor: ArenaContainerId, dir: SplitType)
-        -> ArenaContainerId
+    /// Returns `None` if the descendant is not located below the container.
+    pub fn child_containing(&self, ancestor: ContainerId, descendant: ArenaContainerId)
+        -> Option<ArenaContainerId>
     {
-        let parent = self.containers[cursor].get_parent().expect("cursor is orphaned");
-        let container = SplitContainer::new(dir, (cursor, cursor));
-        let id = self.containers.insert(Container::Split(container));
+        let mut current = descendant;
 
-        let (split, child) = self.containers.get2_mut(id, cursor);
-        split.unwrap().swap_siblings(child.unwrap());
-
-        match parent {
-            ContainerId::Root => self.root.update_children(cursor, id),
-            ContainerId::Index(i) => self.containers[i].update_children(cursor, id),
+        loop {
+            match self.containers.get(current)?.get_parent()? {
+                parent if parent == ancestor => return Some(current),
+                ContainerId::Index(p) => current = p,
+                ContainerId::Root => return None,
+            }
         }
-
-        id
     }
 
+    /// Delete a container and the subtree rooted by it.
+    ///
+    /// Split containers left without children are deleted as well, as they are not allowed to
+    /// exist. Deleting the root clears the tree.
     pub fn delete_container(&mut self, cursor: ContainerId) {
-        let mut cursor = match cursor {
+        let cursor = match cursor {
             ContainerId::Root => {
                 self.root.reset();
                 self.containers.clear();
@@ -276,10 +1496,56 @@ impl<C> TagTree<C> {
             ContainerId::Index(i) => i,
         };
 
+        let descendants: Vec<_> =
+            self.preorder(ContainerId::Index(cursor)).map(|(id, _)| id).collect();
+
+        for id in descendants {
+            self.containers.remove(id);
+        }
+
+        let neighbour = self.unlink_container(cursor);
+        self.containers.remove(cursor);
+
+        self.repair_focus(neighbour);
+    }
+
+    /// Restore the focus and selection markers after containers have been removed.
+    ///
+    /// If the focused client has been removed, the given neighbour of the removed containers
+    /// is focused instead, or the first client in the tree if there is none.
+    fn repair_focus(&mut self, neighbour: Option<ArenaContainerId>) {
+        if self.root.selected.is_some_and(|s| !self.containers.contains(s)) {
+            self.root.selected = None;
+        }
+
+        if self.root.focused.is_some_and(|f| self.containers.contains(f)) {
+            return;
+        }
+
+        self.root.focused = None;
+
+        let target = neighbour
+            .filter(|n| self.containers.contains(*n))
+            .or_else(|| self.root.get_children().map(|c| c.0));
+
+        if let Some(target) = target {
+            self.focus_container(target);
+        }
+    }
+
+    /// Unlink a container from its parent and siblings, leaving it orphaned.
+    ///
+    /// Split containers left without children are deleted, as they are not allowed to exist.
+    /// Returns a sibling of the outermost container removed from the tree structure, if any.
+    fn unlink_container(&mut self, id: ArenaContainerId) -> Option<ArenaContainerId> {
+        let mut cursor = id;
+
         while let Some(parent) = self.containers[cursor].get_parent() {
-            if let Some(prev) = self.containers[cursor].get_prev_sibling() {
-                let succ = self.containers[cursor].get_next_sibling();
-                self.containers[prev].set_next_sibling(succ);
+            let prev = self.containers[cursor].get_prev_sibling();
+            let next = self.containers[cursor].get_next_sibling();
+
+            if let Some(prev) = prev {
+                self.containers[prev].set_next_sibling(next);
 
                 match parent {
                     ContainerId::Root =>
@@ -289,9 +1555,8 @@ impl<C> TagTree<C> {
                 }
             }
 
-            if let Some(next) = self.containers[cursor].get_next_sibling() {
-                let pred = self.containers[cursor].get_prev_sibling();
-                self.containers[next].set_next_sibling(pred);
+            if let Some(next) = next {
+                self.containers[next].set_prev_sibling(prev);
 
                 match parent {
                     ContainerId::Root =>
@@ -301,14 +1566,196 @@ impl<C> TagTree<C> {
                 }
             }
 
-            self.containers.remove(cursor);
+            if cursor == id {
+                let container = &mut self.containers[cursor];
+                container.set_parent(None);
+                container.set_prev_sibling(None);
+                container.set_next_sibling(None);
+            } else {
+                self.containers.remove(cursor);
+            }
 
+            // the parent is left without children if the cursor had no siblings
             match parent {
-                ContainerId::Index(i) if self.num_children(parent) == 1 =>
-                    cursor = i,
-                _ => break,
+                _ if prev.is_some() || next.is_some() => return prev.or(next),
+                ContainerId::Index(p) => cursor = p,
+                ContainerId::Root => {
+                    self.root.reset();
+                    break;
+                },
+            }
+        }
+
+        None
+    }
+
+    /// Remove redundant split containers from the tree.
+    ///
+    /// Split containers with a single child are replaced by that child, and a split container
+    /// that is the only child of the root is merged into the root, which takes over its split
+    /// type. Horizontal and vertical splits with the same orientation as their parent have
+    /// their children moved into the parent, with weights scaled so that every container
+    /// keeps its geometry. Tabbed and stacked splits are never merged into a parent holding
+    /// other children as well, as they would lose their grouping. Floating splits are never
+    /// merged into their parent, but a floating split with a single child passes its floating
+    /// state on to it. Likewise, marks are passed on to a single child, and marked splits are
+    /// kept otherwise.
+    ///
+    /// Returns `true` if the tree has been modified.
+    pub fn normalize(&mut self) -> bool {
+        let mut modified = false;
+
+        while let Some(split) = self.find_redundant_split() {
+            self.dissolve_split(split);
+            modified = true;
+        }
+
+        modified
+    }
+
+    /// Find a split container that can be dissolved by `normalize`, if any.
+    fn find_redundant_split(&self) -> Option<ArenaContainerId> {
+        for (id, container) in self.preorder(ContainerId::Root) {
+            let split = match container {
+                Container::Split(s) => s,
+                Container::Client(_) => continue,
+            };
+
+            // a marked split passes its mark on to its only child, unless that has one, too
+            if self.num_children(ContainerId::Index(id)) == 1 {
+                let (child, _) = split.children;
+
+                if split.mark.is_none() || self.containers[child].mark().is_none() {
+                    return Some(id);
+                }
+            }
+
+            if split.floating || split.mark.is_some() {
+                continue;
+            }
+
+            // a split that is its parent's only child covers the same geometry as the parent,
+            // splits below the root are handled by the single child case above
+            if split.parent == Some(ContainerId::Root) &&
+                self.num_children(ContainerId::Root) == 1
+            {
+                return Some(id);
+            }
+
+            let parent_split_type = split.parent.and_then(|p| self.get_split_type(p));
+
+            if parent_split_type.is_some_and(|t| t.same_orientation(split.split_type)) {
+                return Some(id);
+            }
+        }
+
+        None
+    }
+
+    /// Replace a split container by its children.
+    ///
+    /// If the split is the only child of the root, the root takes over its split type. The
+    /// mark of a split with a single child is passed on to that child. The weights of the
+    /// children and their new siblings are scaled to keep the geometries they are rendered
+    /// with.
+    fn dissolve_split(&mut self, id: ArenaContainerId) {
+        let (split_type, last_split_type, floating, mark, (first, last), parent, prev, next) =
+            match self.containers[id] {
+                Container::Split(ref mut s) => {
+                    let parent = s.parent.expect("split container is orphaned");
+                    (s.split_type, s.last_split_type, s.floating, s.mark.take(), s.children,
+                     parent, s.prev_sibling, s.next_sibling)
+                },
+                Container::Client(_) => panic!("attempted to dissolve client container"),
+            };
+
+        let children: Vec<_> = self.children(ContainerId::Index(id)).map(|(c, _)| c).collect();
+        let siblings: Vec<_> = self.children(parent).map(|(c, _)| c).filter(|c| *c != id).collect();
+
+        // the children share the split's weight, the siblings are scaled by the same factor
+        let weight = self.containers[id].weight();
+        let children_weight: u32 = children.iter().map(|c| self.containers[*c].weight()).sum();
+
+        for child in &children {
+            let scaled = self.containers[*child].weight().saturating_mul(weight);
+
+            self.containers[*child].set_parent(Some(parent));
+            self.containers[*child].set_weight(scaled);
+        }
+
+        for sibling in &siblings {
+            let scaled = self.containers[*sibling].weight().saturating_mul(children_weight);
+
+            self.containers[*sibling].set_weight(scaled);
+        }
+
+        let divisor = children
+            .iter()
+            .chain(&siblings)
+            .fold(0, |d, c| gcd(d, self.containers[*c].weight()));
+
+        for container in children.iter().chain(&siblings) {
+            let reduced = self.containers[*container].weight() / divisor;
+
+            self.containers[*container].set_weight(reduced);
+        }
+
+        if children.len() == 1 {
+            if floating {
+                self.containers[first].set_floating(true);
+            }
+
+            if let Some(mark) = mark {
+                self.containers[first].set_mark(Some(mark));
             }
         }
+
+        self.containers[first].set_prev_sibling(prev);
+        self.containers[last].set_next_sibling(next);
+
+        if let Some(prev) = prev {
+            self.containers[prev].set_next_sibling(Some(first));
+        }
+
+        if let Some(next) = next {
+            self.containers[next].set_prev_sibling(Some(last));
+        }
+
+        match parent {
+            ContainerId::Root => {
+                if prev.is_none() && next.is_none() {
+                    self.root.split_type = split_type;
+                    self.root.last_split_type = last_split_type;
+                }
+
+                self.root.update_first_child(id, first);
+                self.root.update_last_child(id, last);
+            },
+            ContainerId::Index(p) => {
+                self.containers[p].update_first_child(id, first);
+                self.containers[p].update_last_child(id, last);
+            },
+        }
+
+        if self.root.selected == Some(id) {
+            self.root.selected = match parent {
+                _ if children.len() == 1 => Some(first),
+                ContainerId::Index(p) => Some(p),
+                ContainerId::Root => None,
+            };
+        }
+
+        self.containers.remove(id);
+    }
+
+    /// Get all clients in the subtree rooted by a container, including the container itself.
+    pub fn subtree_clients(&self, id: ArenaContainerId) -> Vec<&C> {
+        let mut clients: Vec<_> = self.get_client(id).into_iter().collect();
+
+        clients.extend(self.preorder(ContainerId::Index(id))
+                       .filter_map(|(c, _)| self.get_client(c)));
+
+        clients
     }
 
     pub fn preorder(&self, id: ContainerId) -> TagTreePreorder<C> {
@@ -340,7 +1787,19 @@ impl<C> TagTree<C> {
     }
 
     pub fn num_children(&self, id: ContainerId) -> usize {
-        self.children(id).len()
+        self.children(id).count()
+    }
+}
+
+impl<C: PartialEq> TagTree<C> {
+    /// Find the container holding a client.
+    pub fn find_client(&self, client: &C) -> Option<ArenaContainerId> {
+        self.preorder(ContainerId::Root)
+            .find(|&(_, c)| match c {
+                Container::Client(c) => c.client == *client,
+                Container::Split(_) => false,
+            })
+            .map(|(id, _)| id)
     }
 }
 
@@ -353,14 +1812,15 @@ impl<'a, C> Iterator for TagTreeChildren<'a, C> {
     type Item = (ArenaContainerId, &'a Container<C>);
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.current
-            .and_then(|i| self.tree.containers[i].get_next_sibling())
-            .map(|n| (n, &self.tree.containers[n]))
+        let current = self.current?;
+        let container = &self.tree.containers[current];
+
+        self.current = container.get_next_sibling();
+
+        Some((current, container))
     }
 }
 
-impl<'a, C> ExactSizeIterator for TagTreeChildren<'a, C> { }
-
 pub struct TagTreePreorder<'a, C> {
     tree: &'a TagTree<C>,
     root: ContainerId,
@@ -371,40 +1831,40 @@ impl<'a, C> Iterator for TagTreePreorder<'a, C> {
     type Item = (ArenaContainerId, &'a Container<C>);
 
     fn next(&mut self) -> Option<Self::Item> {
-        match self.current {
-            ContainerId::Root => {
-                if let Some((i, _)) = self.tree.root.get_children() {
-                    self.current = ContainerId::Index(i);
-                    Some((i, &self.tree.containers[i]))
-                } else {
-                    None
-                }
-            },
+        let next = match self.current {
+            ContainerId::Root => self.tree.root.get_children().map(|c| c.0),
             ContainerId::Index(current) => {
-                let c = &self.tree.containers[current];
-
-                if let Some(i) = c.get_children().map(|c| c.0).or_else(|| c.get_next_sibling()) {
-                    self.current = ContainerId::Index(i);
-                    Some((i, &self.tree.containers[i]))
+                if let Some((first, _)) = self.tree.containers[current].get_children() {
+                    Some(first)
                 } else {
-                    while let Some(ContainerId::Index(i)) =
-                        self.tree.containers[current].get_parent()
-                    {
-                        if ContainerId::Index(i) == self.root {
-                            break;
+                    // climb up until a container with a next sibling is found, but never
+                    // leave the subtree we are traversing
+                    let mut current = current;
+
+                    loop {
+                        if ContainerId::Index(current) == self.root {
+                            break None;
                         }
 
-                        self.current = ContainerId::Index(i);
+                        let c = &self.tree.containers[current];
 
-                        if let Some(n) = self.tree.containers[i].get_next_sibling() {
-                            return Some((n, &self.tree.containers[n]));
+                        if let Some(n) = c.get_next_sibling() {
+                            break Some(n);
                         }
-                    }
 
-                    None
+                        match c.get_parent() {
+                            Some(ContainerId::Index(p)) => current = p,
+                            _ => break None,
+                        }
+                    }
                 }
-            }
-        }
+            },
+        };
+
+        next.map(|i| {
+            self.current = ContainerId::Index(i);
+            (i, &self.tree.containers[i])
+        })
     }
 }
 
@@ -418,6 +1878,8 @@ impl<'a, C> Iterator for TagTreePreorder<'a, C> {
 pub struct TagTreeContainer {
     /// The split type at the root.
     pub split_type: SplitType,
+    /// The split type to return to when cycling away from a tabbed or stacked root.
+    last_split_type: SplitType,
     /// The currently focused client.
     focused: Option<ArenaContainerId>,
     /// The currently selected container.
@@ -431,6 +1893,7 @@ impl TagTreeContainer {
     fn new(split_type: SplitType) -> Self {
         TagTreeContainer {
             split_type,
+            last_split_type: split_type.to_split(),
             focused: None,
             selected: None,
             children: None,
@@ -524,6 +1987,59 @@ impl<C> Container<C> {
         }
     }
 
+    fn set_floating(&mut self, floating: bool) {
+        match self {
+            Self::Split(s) => s.floating = floating,
+            Self::Client(c) => c.floating = floating,
+        }
+    }
+
+    /// Get the geometry requested for the container when floating, if any.
+    pub fn floating_geometry(&self) -> Option<Geometry> {
+        match self {
+            Self::Split(_) => None,
+            Self::Client(c) => c.geometry,
+        }
+    }
+
+    /// Get the mark of the container, if any.
+    pub fn mark(&self) -> Option<&str> {
+        match self {
+            Self::Split(s) => s.mark.as_deref(),
+            Self::Client(c) => c.mark.as_deref(),
+        }
+    }
+
+    fn set_mark(&mut self, mark: Option<String>) {
+        match self {
+            Self::Split(s) => s.mark = mark,
+            Self::Client(c) => c.mark = mark,
+        }
+    }
+
+    /// Get the share of the parent's geometry taken up by the container, relative to its
+    /// siblings.
+    pub fn weight(&self) -> u32 {
+        match self {
+            Self::Split(s) => s.weight,
+            Self::Client(c) => c.weight,
+        }
+    }
+
+    fn set_weight(&mut self, weight: u32) {
+        match self {
+            Self::Split(s) => s.weight = weight,
+            Self::Client(c) => c.weight = weight,
+        }
+    }
+
+    pub fn get_split_type(&self) -> Option<SplitType> {
+        match self {
+            Self::Split(s) => Some(s.split_type),
+            Self::Client(_) => None,
+        }
+    }
+
     pub fn last_focused(&self) -> Option<ArenaContainerId> {
         match self {
             Self::Split(s) => s.last_focused,
@@ -603,7 +2119,7 @@ impl<C> Container<C> {
         }
     }
 
-    fn get_prev_sibling(&self) -> Option<ArenaContainerId> {
+    pub fn get_prev_sibling(&self) -> Option<ArenaContainerId> {
         match self {
             Self::Split(s) => s.prev_sibling,
             Self::Client(c) => c.prev_sibling,
@@ -617,7 +2133,7 @@ impl<C> Container<C> {
         }
     }
 
-    fn get_next_sibling(&self) -> Option<ArenaContainerId> {
+    pub fn get_next_sibling(&self) -> Option<ArenaContainerId> {
         match self {
             Self::Split(s) => s.next_sibling,
             Self::Client(c) => c.next_sibling,
@@ -665,8 +2181,14 @@ impl<C> Container<C> {
 pub struct SplitContainer {
     /// The container's split type.
     pub split_type: SplitType,
+    /// The split type to return to when cycling away from a tabbed or stacked container.
+    last_split_type: SplitType,
     /// Whether the entire container is floating.
     pub floating: bool,
+    /// The mark of the container, if any.
+    mark: Option<String>,
+    /// The share of the parent's geometry taken up by the container, relative to its siblings.
+    weight: u32,
     /// the last descendant client container focused.
     last_focused: Option<ArenaContainerId>,
     /// The children of the split (first and last child). 
@@ -689,8 +2211,11 @@ impl SplitContainer {
     fn new(split_type: SplitType, children: (ArenaContainerId, ArenaContainerId)) -> Self {
         SplitContainer {
             split_type,
+            last_split_type: split_type.to_split(),
             last_focused: None,
             floating: false,
+            mark: None,
+            weight: 1,
             children,
             parent: None,
             prev_sibling: None,
@@ -715,6 +2240,12 @@ impl SplitContainer {
 pub struct ClientContainer<C> {
     /// Whether the client is floating.
     pub floating: bool,
+    /// The geometry of the client when floating, centered on the screen if `None`.
+    geometry: Option<Geometry>,
+    /// The mark of the container, if any.
+    mark: Option<String>,
+    /// The share of the parent's geometry taken up by the container, relative to its siblings.
+    weight: u32,
     /// The client information.
     client: C,
     /// The parent of the container.
@@ -733,6 +2264,9 @@ impl<C> ClientContainer<C> {
     fn new(client: C, parent: ContainerId) -> Self {
         ClientContainer {
             floating: false,
+            geometry: None,
+            mark: None,
+            weight: 1,
             client,
             parent: Some(parent),
             prev_sibling: None,
@@ -744,11 +2278,18 @@ impl<C> ClientContainer<C> {
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct SplitRatio(u8);
 
+impl Default for SplitRatio {
+    fn default() -> Self {
+        SplitRatio(50)
+    }
+}
+
 impl SplitRatio {
-    fn new(inner: u8) -> Self {
-        use std::cmp::max;
+    /// Construct a split ratio from a percentage, capped at 100.
+    pub fn new(inner: u8) -> Self {
+        use std::cmp::min;
 
-        SplitRatio(max(inner, 100))
+        SplitRatio(min(inner, 100))
     }
 }
 
@@ -764,9 +2305,9 @@ impl Add<u8> for SplitRatio {
     type Output = SplitRatio;
 
     fn add(self, rhs: u8) -> Self::Output {
-        use std::cmp::max;
+        use std::cmp::min;
 
-        SplitRatio(max(self.0 + rhs, 100))
+        SplitRatio(min(self.0.saturating_add(rhs), 100))
     }
 }
 
@@ -787,4 +2328,380 @@ pub enum SplitType {
     Horizontal(SplitRatio),
     Vertical(SplitRatio),
     Tabbed,
+    /// Like `Tabbed`, but the titles of all children are stacked on top of each other.
+    Stacked,
+}
+
+impl SplitType {
+    /// Check whether only one child of a container with this split type is visible at a time.
+    pub fn shows_single_child(&self) -> bool {
+        matches!(self, SplitType::Tabbed | SplitType::Stacked)
+    }
+
+    /// Check whether containers with this and the other split type lay out their children
+    /// along the same axis.
+    fn same_orientation(self, other: SplitType) -> bool {
+        matches!((self, other),
+                 (SplitType::Horizontal(_), SplitType::Horizontal(_)) |
+                 (SplitType::Vertical(_), SplitType::Vertical(_)))
+    }
+
+    /// Get the split type to remember for a container created with this split type.
+    fn to_split(self) -> SplitType {
+        if self.shows_single_child() {
+            SplitType::Horizontal(SplitRatio::default())
+        } else {
+            self
+        }
+    }
+}
+
+/// Compute the greatest common divisor of two numbers.
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use layout::Manual;
+
+    /// Describe the shape of a subtree, such as `cH(cc)c` for a client, a horizontal split
+    /// holding two clients, and another client.
+    fn shape(tree: &TagTree<u32>, id: ContainerId) -> String {
+        tree.children(id)
+            .map(|(child, container)| match container.get_split_type() {
+                Some(split_type) => {
+                    let name = match split_type {
+                        SplitType::Horizontal(_) => "H",
+                        SplitType::Vertical(_) => "V",
+                        SplitType::Tabbed => "T",
+                        SplitType::Stacked => "S",
+                    };
+
+                    format!("{}({})", name, shape(tree, ContainerId::Index(child)))
+                },
+                None => "c".to_owned(),
+            })
+            .collect()
+    }
+
+    fn vertical() -> SplitType {
+        SplitType::Vertical(SplitRatio::default())
+    }
+
+    fn tags(tags: &[i8]) -> BTreeSet<Tag> {
+        tags.iter().map(|t| Tag::Work(*t)).collect()
+    }
+
+    fn layout() -> Box<Layout<u32>> {
+        Box::new(Manual { title_height: 0 })
+    }
+
+    /// Construct a hierarchy with a single screen showing tag 1, and clients with the given
+    /// ids and tags, added in order.
+    fn hierarchy(clients: &[(u32, i8)]) -> ClientHierarchy<u32> {
+        let mut hierarchy = ClientHierarchy::new();
+        let tagset = TagSet::new(tags(&[1]), TagTree::new(vertical()), layout());
+        let tagset = hierarchy.add_tagset(tagset);
+
+        hierarchy.add_screen(Screen::new(Geometry::new(0, 0, 1200, 800), tagset));
+
+        for &(id, tag) in clients {
+            let client_tags = tags(&[tag]).into_iter().collect();
+            let client = Client::new(id, ClientProperties::default(), client_tags);
+
+            hierarchy.add_client(client, &Placement::default());
+        }
+
+        hierarchy
+    }
+
+    #[test]
+    fn normalize_collapses_single_children() {
+        let mut tree = TagTree::new(vertical());
+        let a = tree.insert_first_client(1);
+        let b = tree.insert_client_after(a, 2);
+        let inner = tree.split_container(b, vertical());
+        tree.split_container(inner, SplitType::Tabbed);
+
+        assert_eq!(shape(&tree, ContainerId::Root), "cT(V(c))");
+        assert!(tree.normalize());
+        assert_eq!(shape(&tree, ContainerId::Root), "cc");
+        assert_eq!(tree.len(), 2);
+        assert!(!tree.normalize());
+    }
+
+    #[test]
+    fn normalize_merges_only_child_into_root() {
+        let mut tree = TagTree::new(vertical());
+        let a = tree.insert_first_client(1);
+        tree.insert_client_after(a, 2);
+        tree.split_container(a, SplitType::Tabbed);
+        let b = tree.find_client(&2).unwrap();
+        tree.move_subtree_after(a, b);
+
+        assert_eq!(shape(&tree, ContainerId::Root), "T(cc)");
+        assert!(tree.normalize());
+        assert_eq!(shape(&tree, ContainerId::Root), "cc");
+        assert_eq!(tree.root.split_type, SplitType::Tabbed);
+    }
+
+    #[test]
+    fn normalize_keeps_marks() {
+        let mut tree = TagTree::new(vertical());
+        let a = tree.insert_first_client(1);
+        let b = tree.insert_client_after(a, 2);
+        let single = tree.split_container(b, SplitType::Tabbed);
+        tree.set_mark(single, Some("single".to_owned()));
+
+        assert!(tree.normalize());
+        assert_eq!(tree.find_mark("single"), Some(b));
+
+        // both containers are marked, so the split stays
+        let split = tree.split_container(b, SplitType::Tabbed);
+        tree.set_mark(split, Some("split".to_owned()));
+
+        assert!(!tree.normalize());
+        assert_eq!(shape(&tree, ContainerId::Root), "cT(c)");
+        assert_eq!(tree.find_mark("split"), Some(split));
+
+        // marked splits are not merged into the root
+        tree.set_mark(b, None);
+        tree.insert_client_after(b, 3);
+        tree.delete_container(ContainerId::Index(a));
+
+        assert!(!tree.normalize());
+        assert_eq!(shape(&tree, ContainerId::Root), "T(cc)");
+    }
+
+    /// Render a tree with the manual layout and collect the geometries of its clients.
+    fn client_geometries(tree: &TagTree<u32>) -> Vec<(u32, Geometry)> {
+        let layout = Manual { title_height: 10 };
+        let mut sizes = HashMap::new();
+        let mut titles = HashMap::new();
+
+        layout.render(tree, &Geometry::new(0, 0, 1200, 800), &mut sizes, &mut titles);
+
+        let mut geometries: Vec<_> = sizes
+            .into_iter()
+            .filter_map(|(id, geo)| match id {
+                ContainerId::Index(i) => tree.get_client(i).map(|c| (*c, geo)),
+                ContainerId::Root => None,
+            })
+            .collect();
+
+        geometries.sort_by_key(|&(c, _)| c);
+        geometries
+    }
+
+    #[test]
+    fn normalize_merges_same_orientation_splits() {
+        let mut tree = TagTree::new(vertical());
+        let a = tree.insert_first_client(1);
+        let b = tree.insert_client_after(a, 2);
+        tree.split_container(b, vertical());
+        let c = tree.insert_client_after(b, 3);
+        let geometries = client_geometries(&tree);
+
+        // the clients keep their halves and quarters of the screen
+        assert_eq!(shape(&tree, ContainerId::Root), "cV(cc)");
+        assert!(tree.normalize());
+        assert_eq!(shape(&tree, ContainerId::Root), "ccc");
+        assert_eq!(client_geometries(&tree), geometries);
+
+        let weights: Vec<_> = [a, b, c].iter().map(|i| tree.containers[*i].weight()).collect();
+        assert_eq!(weights, [2, 1, 1]);
+
+        // splits with the other orientation are kept
+        tree.split_container(c, SplitType::Horizontal(SplitRatio::default()));
+        tree.insert_client_after(c, 4);
+
+        assert!(!tree.normalize());
+        assert_eq!(shape(&tree, ContainerId::Root), "ccH(cc)");
+    }
+
+    #[test]
+    fn normalize_keeps_nested_tabbed_splits() {
+        let mut tree = TagTree::new(SplitType::Tabbed);
+        let a = tree.insert_first_client(1);
+        let b = tree.insert_client_after(a, 2);
+        tree.split_container(b, SplitType::Tabbed);
+        tree.insert_client_after(b, 3);
+
+        assert_eq!(shape(&tree, ContainerId::Root), "cT(cc)");
+        assert!(!tree.normalize());
+    }
+
+    #[test]
+    fn scratchpad_clients_stay_hidden() {
+        let mut hierarchy = hierarchy(&[(1, 1), (2, 1)]);
+        let in_tag_trees = |h: &ClientHierarchy<u32>| {
+            h.tagsets().any(|(_, t)| t.tree().find_client(&1).is_some())
+        };
+
+        hierarchy.add_scratchpad("term", Vec::new());
+        assert!(hierarchy.move_to_scratchpad("term", 1));
+        assert!(!in_tag_trees(&hierarchy));
+
+        // viewing the former tags creates a new tagset without the hidden client
+        hierarchy.view_tags(tags(&[1, 2]), layout());
+        assert!(!in_tag_trees(&hierarchy));
+
+        assert!(hierarchy.set_client_tags(&1, tags(&[2]).into_iter().collect()));
+        assert!(!in_tag_trees(&hierarchy));
+        assert_eq!(hierarchy.hidden_scratchpad_clients(), [&1]);
+    }
+
+    #[test]
+    fn marks_are_unique_across_tagsets() {
+        let mut hierarchy = hierarchy(&[(1, 1), (2, 1), (3, 2)]);
+        let first = hierarchy.get_focused_tagset().unwrap();
+        let second = hierarchy.view_tags(tags(&[2]), layout()).unwrap();
+        let find = |h: &ClientHierarchy<u32>, tagset, client| {
+            h.get_tagset(tagset).unwrap().tree().find_client(&client).unwrap()
+        };
+        let (a, c) = (find(&hierarchy, first, 1), find(&hierarchy, second, 3));
+
+        assert!(hierarchy.mark_container(first, a, "x"));
+        assert_eq!(hierarchy.find_mark("x"), Some((first, a)));
+
+        assert!(hierarchy.mark_container(second, c, "x"));
+        assert_eq!(hierarchy.find_mark("x"), Some((second, c)));
+        assert_eq!(hierarchy.get_tagset(first).unwrap().tree().find_mark("x"), None);
+
+        assert!(hierarchy.unmark("x"));
+        assert!(!hierarchy.unmark("x"));
+        assert_eq!(hierarchy.find_mark("x"), None);
+    }
+
+    #[test]
+    fn focus_mark_shows_the_tagset() {
+        let mut hierarchy = hierarchy(&[(1, 1), (2, 1), (3, 2)]);
+        let first = hierarchy.get_focused_tagset().unwrap();
+        hierarchy.view_tags(tags(&[2]), layout());
+        let a = hierarchy.get_tagset(first).unwrap().tree().find_client(&1).unwrap();
+
+        assert!(hierarchy.mark_container(first, a, "x"));
+        assert!(hierarchy.focus_mark("x"));
+        assert_eq!(hierarchy.get_focused_tagset(), Some(first));
+        assert_eq!(hierarchy.get_focused_client(), Some(&1));
+
+        assert!(!hierarchy.focus_mark("y"));
+    }
+
+    /// Focus a client and record it, as arranging the clients after a request does.
+    fn focus(hierarchy: &mut ClientHierarchy<u32>, client: u32) {
+        assert!(hierarchy.focus_client(&client));
+        hierarchy.record_focus(&client);
+    }
+
+    fn record_focused(hierarchy: &mut ClientHierarchy<u32>) {
+        if let Some(client) = hierarchy.get_focused_client().cloned() {
+            hierarchy.record_focus(&client);
+        }
+    }
+
+    #[test]
+    fn focus_previous_alternates() {
+        let mut hierarchy = hierarchy(&[(1, 1), (2, 1), (3, 1)]);
+
+        for client in 1..4 {
+            focus(&mut hierarchy, client);
+        }
+
+        assert_eq!(hierarchy.focus_order(false), [3, 2, 1]);
+
+        assert!(hierarchy.focus_previous(false));
+        record_focused(&mut hierarchy);
+        assert_eq!(hierarchy.get_focused_client(), Some(&2));
+
+        assert!(hierarchy.focus_previous(false));
+        record_focused(&mut hierarchy);
+        assert_eq!(hierarchy.get_focused_client(), Some(&3));
+        assert_eq!(hierarchy.focus_order(false), [3, 2, 1]);
+
+        hierarchy.remove_client(&3);
+        assert_eq!(hierarchy.focus_order(false), [2, 1]);
+    }
+
+    #[test]
+    fn cycles_preview_without_reordering() {
+        let mut hierarchy = hierarchy(&[(1, 1), (2, 1), (3, 1)]);
+
+        for client in 1..4 {
+            focus(&mut hierarchy, client);
+        }
+
+        for &expected in &[2, 1, 3] {
+            assert!(hierarchy.cycle_focus(false, true));
+            record_focused(&mut hierarchy);
+            assert_eq!(hierarchy.get_focused_client(), Some(&expected));
+            assert_eq!(hierarchy.focus_order(false), [3, 2, 1]);
+        }
+
+        assert!(hierarchy.cycle_focus(false, false));
+        record_focused(&mut hierarchy);
+        assert!(hierarchy.end_cycle());
+        assert!(!hierarchy.end_cycle());
+        assert_eq!(hierarchy.focus_order(false), [1, 3, 2]);
+
+        // focusing another client ends the cycle, keeping the client previewed
+        assert!(hierarchy.cycle_focus(false, true));
+        focus(&mut hierarchy, 2);
+        assert!(!hierarchy.end_cycle());
+        assert_eq!(hierarchy.focus_order(false), [2, 3, 1]);
+    }
+
+    #[test]
+    fn global_cycles_show_other_tagsets() {
+        let mut hierarchy = hierarchy(&[(1, 1), (2, 1)]);
+        let first = hierarchy.get_focused_tagset().unwrap();
+        let second =
+            hierarchy.add_tagset(TagSet::new(tags(&[2]), TagTree::new(vertical()), layout()));
+        let client = Client::new(9, ClientProperties::default(), tags(&[2]).into_iter().collect());
+
+        hierarchy.add_client(client, &Placement::default());
+        focus(&mut hierarchy, 1);
+
+        assert_eq!(hierarchy.focus_order(false), [1, 2]);
+        assert_eq!(hierarchy.focus_order(true), [1, 2, 9]);
+
+        assert!(hierarchy.cycle_focus(true, true));
+        assert!(hierarchy.cycle_focus(true, true));
+        record_focused(&mut hierarchy);
+        assert_eq!(hierarchy.get_focused_tagset(), Some(second));
+        assert_eq!(hierarchy.get_focused_client(), Some(&9));
+
+        assert!(hierarchy.end_cycle());
+        assert_eq!(hierarchy.focus_order(true), [9, 1, 2]);
+
+        assert!(hierarchy.focus_previous(true));
+        record_focused(&mut hierarchy);
+        assert_eq!(hierarchy.get_focused_tagset(), Some(first));
+        assert_eq!(hierarchy.get_focused_client(), Some(&1));
+    }
+
+    #[test]
+    fn toggle_split_type_remembers_the_split() {
+        let mut tree = TagTree::new(vertical());
+        let a = tree.insert_first_client(1);
+        tree.insert_client_after(a, 2);
+
+        let toggled: Vec<_> =
+            (0..3).filter_map(|_| tree.toggle_split_type(ContainerId::Root)).collect();
+        assert_eq!(toggled, [SplitType::Tabbed, SplitType::Stacked, vertical()]);
+
+        let horizontal = SplitType::Horizontal(SplitRatio::default());
+        assert!(tree.set_split_type(ContainerId::Root, horizontal));
+        assert!(tree.set_split_type(ContainerId::Root, SplitType::Stacked));
+        assert_eq!(tree.toggle_split_type(ContainerId::Root), Some(horizontal));
+
+        assert!(!tree.set_split_type(ContainerId::Index(a), SplitType::Tabbed));
+        assert_eq!(tree.toggle_split_type(ContainerId::Index(a)), None);
+    }
 }
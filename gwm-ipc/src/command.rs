@@ -0,0 +1,325 @@
+//! Parsing of requests from the command syntax shared by `gwmc` and the key binding daemon.
+//!
+//! A command is a sequence of whitespace-separated words, such as `focus left`,
+//! `view +3 +4` or `layout msg 0 -5`. Commands acting on the focused client can be prefixed
+//! with criteria selecting the clients to act on instead, as in `[class="mpv"] float` or
+//! `[tag=3] focus`.
+
+use std::str::FromStr;
+
+use super::*;
+
+/// Result type for parsing commands, carrying a message describing the problem on failure.
+pub type ParseResult<T> = Result<T, String>;
+
+fn invalid<T, S: Into<String>>(message: S) -> ParseResult<T> {
+    Err(message.into())
+}
+
+/// Parse a direction.
+fn parse_direction(arg: &str) -> ParseResult<Direction> {
+    match arg {
+        "left" => Ok(Direction::Left),
+        "up" => Ok(Direction::Up),
+        "right" => Ok(Direction::Right),
+        "down" => Ok(Direction::Down),
+        "next" => Ok(Direction::Next),
+        "prev" => Ok(Direction::Prev),
+        d => invalid(format!("invalid direction: {}", d)),
+    }
+}
+
+/// Parse an id, given in decimal or, prefixed with `0x`, in hexadecimal.
+fn parse_id(arg: &str) -> ParseResult<u64> {
+    let result = match arg.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => arg.parse(),
+    };
+
+    result.or_else(|_| invalid(format!("invalid id: {}", arg)))
+}
+
+/// Parse a window id.
+fn parse_window(arg: &str) -> ParseResult<u32> {
+    match parse_id(arg)? {
+        id if id <= u64::from(u32::MAX) => Ok(id as u32),
+        _ => invalid(format!("invalid window: {}", arg)),
+    }
+}
+
+/// Parse the direction of a cycle through the focus history, returning whether it is
+/// reversed.
+fn parse_cycle(arg: &str) -> ParseResult<bool> {
+    match arg {
+        "next" => Ok(false),
+        "prev" => Ok(true),
+        d => invalid(format!("invalid cycle direction: {}", d)),
+    }
+}
+
+/// Parse a list of tags, all prefixed with `+` (add), `-` (remove), `^` (toggle) or nothing
+/// (set).
+fn parse_tags(args: &[&str]) -> ParseResult<(Vec<String>, TagMode)> {
+    let mut mode = None;
+    let mut tags = Vec::new();
+
+    for arg in args {
+        let (m, tag) = match arg.chars().next() {
+            Some('+') => (TagMode::Add, &arg[1..]),
+            Some('-') => (TagMode::Remove, &arg[1..]),
+            Some('^') => (TagMode::Toggle, &arg[1..]),
+            _ => (TagMode::Set, &arg[..]),
+        };
+
+        if mode.is_some_and(|mode| mode != m) {
+            return invalid("all tags need to have the same prefix");
+        }
+
+        mode = Some(m);
+        tags.push(tag.to_owned());
+    }
+
+    match mode {
+        Some(mode) => Ok((tags, mode)),
+        None => invalid("missing tags"),
+    }
+}
+
+/// Parse a split type.
+fn parse_split(arg: &str) -> ParseResult<Split> {
+    match arg {
+        "horizontal" => Ok(Split::Horizontal),
+        "vertical" => Ok(Split::Vertical),
+        "tabbed" => Ok(Split::Tabbed),
+        "stacked" => Ok(Split::Stacked),
+        s => invalid(format!("invalid split: {}", s)),
+    }
+}
+
+/// Parse the arguments of a layout command, either a layout message `msg PARAM VALUE`, where
+/// values prefixed with `+` or `-` are relative, or a split type or `toggle` to change the
+/// split type of the focused container's parent.
+fn parse_layout(args: &[&str]) -> ParseResult<Request> {
+    let (param, value) = match args {
+        ["msg", param, value] => (param, value),
+        ["toggle"] => return Ok(Request::ToggleSplit),
+        [split] => return Ok(Request::SetSplit { split: parse_split(split)? }),
+        _ => return invalid("expected layout msg PARAM VALUE or layout SPLIT|toggle"),
+    };
+
+    let param = match param.parse() {
+        Ok(p) => p,
+        Err(_) => return invalid(format!("invalid parameter: {}", param)),
+    };
+
+    let relative = value.starts_with('+') || value.starts_with('-');
+    let value = match value.trim_start_matches('+').parse() {
+        Ok(v) => v,
+        Err(_) => return invalid(format!("invalid value: {}", value)),
+    };
+
+    Ok(Request::Layout { param, value, relative })
+}
+
+/// Parse an event class.
+fn parse_event_class(arg: &str) -> ParseResult<EventClass> {
+    match arg {
+        "tagset" => Ok(EventClass::Tagset),
+        "focus" => Ok(EventClass::Focus),
+        "title" => Ok(EventClass::Title),
+        "urgency" => Ok(EventClass::Urgency),
+        "layout" => Ok(EventClass::Layout),
+        "screen" => Ok(EventClass::Screen),
+        e => invalid(format!("invalid event class: {}", e)),
+    }
+}
+
+/// Parse a request from the words of a command.
+pub fn parse_command<S: AsRef<str>>(words: &[S]) -> ParseResult<Request> {
+    let words: Vec<&str> = words.iter().map(|w| w.as_ref()).collect();
+
+    if !words.first().is_some_and(|w| w.starts_with('[')) {
+        return match parse_plain_command(&words)? {
+            Request::Focus { direction: None, .. } => invalid("missing direction"),
+            request => Ok(request),
+        };
+    }
+
+    // criteria containing spaces may have been split into several words
+    let end = match words.iter().position(|w| w.ends_with(']')) {
+        Some(end) => end,
+        None => return invalid("unterminated criteria"),
+    };
+
+    let selected = words[..=end].join(" ");
+
+    match parse_plain_command(&words[end + 1..])? {
+        Request::Focus { direction: None, .. } =>
+            Ok(Request::Focus { direction: None, criteria: Some(selected) }),
+        Request::Float { .. } => Ok(Request::Float { criteria: Some(selected) }),
+        Request::Close { .. } => Ok(Request::Close { criteria: Some(selected) }),
+        Request::Tag { tags, mode, .. } =>
+            Ok(Request::Tag { tags, mode, criteria: Some(selected) }),
+        Request::MoveToScratchpad { name, .. } =>
+            Ok(Request::MoveToScratchpad { name, criteria: Some(selected) }),
+        _ => invalid("criteria can only be given for focus, float, close, tag and scratchpad move"),
+    }
+}
+
+/// Parse a request from the words of a command not prefixed with criteria.
+fn parse_plain_command(words: &[&str]) -> ParseResult<Request> {
+    let (command, args) = match words.split_first() {
+        Some((c, a)) => (*c, a),
+        None => return invalid("missing command"),
+    };
+
+    let request = match (command, args) {
+        ("focus", ["urgent"]) => Request::FocusUrgent,
+        ("focus", ["window", w]) => Request::FocusWindow { window: parse_window(w)? },
+        ("focus", ["screen", s]) => Request::FocusScreen {
+            screen: s.parse().or_else(|_| invalid(format!("invalid screen: {}", s)))?,
+        },
+        ("focus", ["tagset", t]) => Request::FocusTagset { tagset: parse_id(t)? },
+        ("focus", ["previous"]) => Request::FocusPrevious { global: false },
+        ("focus", ["previous", "global"]) => Request::FocusPrevious { global: true },
+        ("cycle", ["end"]) => Request::EndCycle,
+        ("cycle", [d]) => Request::Cycle { reverse: parse_cycle(d)?, global: false },
+        ("cycle", [d, "global"]) => Request::Cycle { reverse: parse_cycle(d)?, global: true },
+        ("focus", ["mark", name]) => Request::FocusMark { name: name.to_string() },
+        ("focus", []) => Request::Focus { direction: None, criteria: None },
+        ("focus", [d]) => Request::Focus { direction: Some(parse_direction(d)?), criteria: None },
+        ("jump", []) => return invalid("missing query"),
+        ("jump", _) => Request::Jump { query: args.join(" ") },
+        ("move", ["mark", name]) => Request::MoveToMark { name: name.to_string() },
+        ("swap", ["mark", name]) => Request::SwapMark { name: name.to_string() },
+        ("move", [d]) => Request::Move { direction: parse_direction(d)? },
+        ("swap", [d]) => Request::Swap { direction: parse_direction(d)? },
+        ("split", [s]) => Request::Split { split: parse_split(s)? },
+        ("mark", [name]) => Request::Mark { name: name.to_string() },
+        ("unmark", [name]) => Request::Unmark { name: name.to_string() },
+        ("float", []) => Request::Float { criteria: None },
+        ("fullscreen", []) => Request::Fullscreen { mode: Fullscreen::Screen },
+        ("fullscreen", [m]) => Request::Fullscreen {
+            mode: match *m {
+                "screen" => Fullscreen::Screen,
+                "container" => Fullscreen::Container,
+                m => return invalid(format!("invalid fullscreen mode: {}", m)),
+            },
+        },
+        ("close", []) => Request::Close { criteria: None },
+        ("tag", _) => {
+            let (tags, mode) = parse_tags(args)?;
+            Request::Tag { tags, mode, criteria: None }
+        },
+        ("view", _) => {
+            let (tags, mode) = parse_tags(args)?;
+            Request::View { tags, mode }
+        },
+        ("layout", _) => parse_layout(args)?,
+        ("scratchpad", ["toggle", name]) => Request::ToggleScratchpad { name: name.to_string() },
+        ("scratchpad", ["move", name]) =>
+            Request::MoveToScratchpad { name: name.to_string(), criteria: None },
+        ("get", ["screens"]) => Request::GetScreens,
+        ("get", ["clients"]) => Request::GetClients,
+        ("get", ["tagsets"]) => Request::GetTagsets,
+        ("subscribe", []) => Request::Subscribe {
+            events: vec![EventClass::Tagset, EventClass::Focus, EventClass::Title,
+                         EventClass::Urgency, EventClass::Layout, EventClass::Screen],
+        },
+        ("subscribe", _) => Request::Subscribe {
+            events: args.iter().map(|a| parse_event_class(a)).collect::<ParseResult<_>>()?,
+        },
+        _ => return invalid(format!("invalid command: {}", words.join(" "))),
+    };
+
+    Ok(request)
+}
+
+impl FromStr for Request {
+    type Err = String;
+
+    fn from_str(command: &str) -> ParseResult<Request> {
+        parse_command(&command.split_whitespace().collect::<Vec<_>>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(command: &str) -> ParseResult<Request> {
+        command.parse()
+    }
+
+    fn tags(tags: &[&str], mode: TagMode) -> ParseResult<(Vec<String>, TagMode)> {
+        Ok((tags.iter().map(|t| t.to_string()).collect(), mode))
+    }
+
+    fn layout(param: usize, value: isize, relative: bool) -> ParseResult<Request> {
+        Ok(Request::Layout { param, value, relative })
+    }
+
+    #[test]
+    fn parse_tags_with_modes() {
+        assert_eq!(parse_tags(&["1", "web"]), tags(&["1", "web"], TagMode::Set));
+        assert_eq!(parse_tags(&["+3", "+4"]), tags(&["3", "4"], TagMode::Add));
+        assert_eq!(parse_tags(&["-3"]), tags(&["3"], TagMode::Remove));
+        assert_eq!(parse_tags(&["^3"]), tags(&["3"], TagMode::Toggle));
+        assert!(parse_tags(&["+3", "-4"]).is_err());
+        assert!(parse_tags(&["3", "^4"]).is_err());
+        assert!(parse_tags(&[]).is_err());
+    }
+
+    #[test]
+    fn parse_layout_commands() {
+        assert_eq!(parse("layout msg 0 -5"), layout(0, -5, true));
+        assert_eq!(parse("layout msg 1 +5"), layout(1, 5, true));
+        assert_eq!(parse("layout msg 1 20"), layout(1, 20, false));
+        assert_eq!(parse("layout tabbed"), Ok(Request::SetSplit { split: Split::Tabbed }));
+        assert_eq!(parse("layout toggle"), Ok(Request::ToggleSplit));
+        assert!(parse("layout msg x 5").is_err());
+        assert!(parse("layout msg 0 x").is_err());
+        assert!(parse("layout diagonal").is_err());
+        assert!(parse("layout").is_err());
+    }
+
+    #[test]
+    fn parse_commands_with_criteria() {
+        assert_eq!(
+            parse(r#"[class="mpv" title="a  b"] float"#),
+            Ok(Request::Float { criteria: Some(r#"[class="mpv" title="a b"]"#.to_owned()) })
+        );
+        assert_eq!(
+            parse("[tag=3] focus"),
+            Ok(Request::Focus { direction: None, criteria: Some("[tag=3]".to_owned()) })
+        );
+        assert_eq!(
+            parse("[urgent=true] tag +2"),
+            Ok(Request::Tag {
+                tags: vec!["2".to_owned()],
+                mode: TagMode::Add,
+                criteria: Some("[urgent=true]".to_owned()),
+            })
+        );
+        assert!(parse("[tag=3 focus").is_err());
+        assert!(parse("[tag=3] focus left").is_err());
+        assert!(parse("[tag=3] view 3").is_err());
+        assert!(parse("focus").is_err());
+    }
+
+    #[test]
+    fn parse_focus_commands() {
+        assert_eq!(parse("focus window 0x1a"), Ok(Request::FocusWindow { window: 26 }));
+        assert!(parse("focus window 0x100000000").is_err());
+        assert_eq!(parse("focus previous global"), Ok(Request::FocusPrevious { global: true }));
+        assert_eq!(parse("cycle prev"), Ok(Request::Cycle { reverse: true, global: false }));
+        assert_eq!(parse("cycle next global"), Ok(Request::Cycle { reverse: false, global: true }));
+        assert_eq!(parse("cycle end"), Ok(Request::EndCycle));
+        assert!(parse("cycle sideways").is_err());
+        assert_eq!(
+            parse("jump Google  Meet"),
+            Ok(Request::Jump { query: "Google Meet".to_owned() })
+        );
+        assert!(parse("jump").is_err());
+    }
+}
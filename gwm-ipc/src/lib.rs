@@ -0,0 +1,699 @@
+//! The control protocol spoken on the window manager's Unix domain socket.
+//!
+//! The socket is located at `$XDG_RUNTIME_DIR/gwm-<display>.sock`, unless `$GWM_SOCKET` names
+//! a different path. Clients send requests as JSON objects, each terminated by a newline, and
+//! receive exactly one response line per request, in order:
+//!
+//! ```text
+//! > {"command": "focus", "direction": "left"}
+//! < {"status": "ok"}
+//! > {"command": "view", "tags": ["3"], "mode": "add"}
+//! < {"status": "ok"}
+//! > {"command": "get_screens"}
+//! < {"status": "ok", "reply": {"screens": [...]}}
+//! > {"command": "frobnicate"}
+//! < {"status": "error", "error": "invalid_request", "message": "..."}
+//! ```
+//!
+//! Failed requests never terminate the connection, and leave the state unchanged.
+//!
+//! Some requests acting on the focused client or container accept `criteria` instead, such
+//! as `[class="Firefox" title="~.*Meet.*" tag=3]`, and then act on all clients matching them.
+//! Values prefixed with `~` are regular expressions.
+//!
+//! Queries identify clients by their window, screens by their index, and tagsets by an id
+//! that stays valid for the lifetime of the window manager process. These ids can be passed
+//! to follow-up requests, such as `focus_window` or `focus_tagset`. Containers can also be
+//! given names with `mark`, unique across all tagsets, which tagset queries show and
+//! `focus_mark`, `swap_mark` and `move_to_mark` accept. The latter two only accept marks in
+//! the focused tagset.
+//!
+//! After subscribing to classes of events, a client additionally receives a line for each
+//! event of those classes as it happens. Events carry an `event` key instead of `status`:
+//!
+//! ```text
+//! > {"command": "subscribe", "events": ["tagset", "focus"]}
+//! < {"status": "ok"}
+//! < {"event": "tagset", "screen": 0, "tags": ["2"]}
+//! < {"event": "focus", "screen": 0, "window": 8388621}
+//! ```
+//!
+//! This crate holds the protocol's message types, shared by the window manager, the key
+//! binding daemon and `gwmc`, along with a blocking client to send requests with.
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use std::collections::VecDeque;
+use std::env;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+mod command;
+
+pub use command::{parse_command, ParseResult};
+
+/// A direction in which containers are looked up, relative to the focused one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Left,
+    Up,
+    Right,
+    Down,
+    /// The next client in the tree, wrapping around.
+    Next,
+    /// The previous client in the tree, wrapping around.
+    Prev,
+}
+
+/// The kind of split container created around the focused container.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Split {
+    Horizontal,
+    Vertical,
+    Tabbed,
+    Stacked,
+}
+
+/// The extent of a fullscreen client.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Fullscreen {
+    /// The client covers its screen.
+    Screen,
+    /// The client covers the container it is located in.
+    Container,
+}
+
+/// How the tags given in a request are combined with the current ones.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TagMode {
+    /// Replace the current tags.
+    #[default]
+    Set,
+    /// Add the tags to the current ones.
+    Add,
+    /// Remove the tags from the current ones.
+    Remove,
+    /// Add the tags not present, and remove the ones present.
+    Toggle,
+}
+
+/// A class of events clients can subscribe to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventClass {
+    /// The tags shown on a screen have changed.
+    Tagset,
+    /// The focused client has changed.
+    Focus,
+    /// The title of a client has changed.
+    Title,
+    /// A client has become urgent or stopped being urgent.
+    Urgency,
+    /// The arrangement of the clients shown on a screen has changed.
+    Layout,
+    /// A screen has been added or removed.
+    Screen,
+}
+
+/// An event sent to the clients subscribed to its class.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    Tagset { screen: usize, tags: Vec<String> },
+    Focus { screen: usize, window: Option<u32> },
+    Title { window: u32, title: String },
+    Urgency { window: u32, urgent: bool },
+    Layout { screen: usize },
+    Screen { screen: usize, added: bool },
+}
+
+impl Event {
+    /// Get the class of the event.
+    pub fn class(&self) -> EventClass {
+        match self {
+            Event::Tagset { .. } => EventClass::Tagset,
+            Event::Focus { .. } => EventClass::Focus,
+            Event::Title { .. } => EventClass::Title,
+            Event::Urgency { .. } => EventClass::Urgency,
+            Event::Layout { .. } => EventClass::Layout,
+            Event::Screen { .. } => EventClass::Screen,
+        }
+    }
+}
+
+/// A request sent to the window manager.
+///
+/// Tags are given by their names, as printed in the status line (`"1"`, `"nonwork"`).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Request {
+    /// Focus the container in the given direction, or the first client matching criteria.
+    Focus {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        direction: Option<Direction>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        criteria: Option<String>,
+    },
+    /// Focus the client that most recently became urgent.
+    FocusUrgent,
+    /// Focus a client, showing a tagset containing it on the focused screen if necessary.
+    FocusWindow { window: u32 },
+    /// Focus the client whose title, class or instance matches a query best, preferring the
+    /// most recently focused one, and show a tagset containing it if necessary.
+    ///
+    /// The query is a regular expression if prefixed with `~`, and a case-insensitive
+    /// substring otherwise.
+    Jump { query: String },
+    /// Focus the client focused before the focused one, in the focused tagset or, if `global`
+    /// is set, in any tagset, which is shown if necessary.
+    FocusPrevious {
+        #[serde(default)]
+        global: bool,
+    },
+    /// Preview the next client in the focus history, or the previous one if `reverse` is set,
+    /// considering clients as `focus_previous` does.
+    ///
+    /// The history keeps its order until the cycle ends, either by `end_cycle` or by focusing
+    /// another client.
+    Cycle {
+        #[serde(default)]
+        reverse: bool,
+        #[serde(default)]
+        global: bool,
+    },
+    /// End the cycle through the focus history, keeping the client previewed focused.
+    EndCycle,
+    /// Focus a screen.
+    FocusScreen { screen: usize },
+    /// Focus a tagset, showing it on the focused screen unless another screen shows it.
+    FocusTagset { tagset: u64 },
+    /// Focus the container with a mark, showing its tagset if necessary.
+    FocusMark { name: String },
+    /// Move the focused container past its neighbour in the given direction.
+    Move { direction: Direction },
+    /// Swap the focused container with its neighbour in the given direction.
+    Swap { direction: Direction },
+    /// Swap the focused container with the container with a mark.
+    ///
+    /// Unlike `focus_mark`, this only works if the mark is in the focused tagset, and fails
+    /// with `unsupported` otherwise: the clients in a tagset are determined by their tags, so
+    /// containers can't be moved between tagsets without retagging their clients.
+    SwapMark { name: String },
+    /// Move the focused container next to the container with a mark.
+    ///
+    /// Like `swap_mark`, this only works if the mark is in the focused tagset.
+    MoveToMark { name: String },
+    /// Wrap the focused container in a new split container.
+    Split { split: Split },
+    /// Change the split type of the split container holding the focused container.
+    SetSplit { split: Split },
+    /// Cycle the split type of the split container holding the focused container from split
+    /// to tabbed to stacked, and back to the split it was before.
+    ToggleSplit,
+    /// Mark the focused container with a name, removing the mark from any other container.
+    Mark { name: String },
+    /// Remove a mark.
+    Unmark { name: String },
+    /// Toggle whether the focused container, or the clients matching criteria, are floating.
+    Float {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        criteria: Option<String>,
+    },
+    /// Toggle the given fullscreen mode of the focused client.
+    Fullscreen { mode: Fullscreen },
+    /// Close the focused container, or the clients matching criteria, killing clients that
+    /// don't react in time.
+    Close {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        criteria: Option<String>,
+    },
+    /// Change the tags of the focused client, or the clients matching criteria.
+    Tag {
+        tags: Vec<String>,
+        #[serde(default)]
+        mode: TagMode,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        criteria: Option<String>,
+    },
+    /// Change the tags shown on the focused screen.
+    View {
+        tags: Vec<String>,
+        #[serde(default)]
+        mode: TagMode,
+    },
+    /// Send a message to the layout of the focused tagset, setting a parameter or adding to
+    /// it if `relative` is set. Fails if the layout doesn't process the message.
+    Layout {
+        param: usize,
+        value: isize,
+        #[serde(default)]
+        relative: bool,
+    },
+    /// Toggle the visibility of a scratchpad on the focused screen.
+    ToggleScratchpad { name: String },
+    /// Move the focused client, or the clients matching criteria, to a scratchpad.
+    MoveToScratchpad {
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        criteria: Option<String>,
+    },
+    /// Query all screens.
+    GetScreens,
+    /// Query all managed clients.
+    GetClients,
+    /// Query all tagsets, along with their container trees.
+    GetTagsets,
+    /// Receive the events of the given classes on this connection, replacing earlier
+    /// subscriptions.
+    Subscribe { events: Vec<EventClass> },
+}
+
+/// A rectangle on the screen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// The state of a screen.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScreenInfo {
+    /// The index of the screen.
+    pub index: usize,
+    /// The geometry of the screen.
+    pub geometry: Rect,
+    /// The part of the screen not reserved by docks.
+    pub area: Rect,
+    /// The id of the tagset shown on the screen.
+    pub tagset: u64,
+    /// The tags shown on the screen.
+    pub tags: Vec<String>,
+    /// Whether the screen is focused.
+    pub focused: bool,
+}
+
+/// The state of a managed client.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClientInfo {
+    /// The client's window.
+    pub window: u32,
+    /// The client's title.
+    pub title: String,
+    /// The `WM_CLASS` class name of the client.
+    pub class: Option<String>,
+    /// The `WM_CLASS` instance name of the client.
+    pub instance: Option<String>,
+    /// The types of the client's window, in order of preference, such as `"dialog"`.
+    pub window_type: Vec<String>,
+    /// The window the client is transient for.
+    pub transient_for: Option<u32>,
+    /// The process id of the client.
+    pub pid: Option<u32>,
+    /// The tags of the client.
+    pub tags: Vec<String>,
+    /// Whether the client is urgent.
+    pub urgent: bool,
+    /// Whether the client is fullscreen, and how.
+    pub fullscreen: Option<Fullscreen>,
+    /// Whether the client is the focused one.
+    pub focused: bool,
+}
+
+/// A node in the container tree of a tagset.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Node {
+    /// A split container, holding its children in order.
+    Split {
+        split: Split,
+        floating: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        mark: Option<String>,
+        children: Vec<Node>,
+    },
+    /// A container holding a client.
+    Client {
+        window: u32,
+        floating: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        mark: Option<String>,
+    },
+}
+
+/// The state of a tagset.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TagsetInfo {
+    /// The id of the tagset.
+    pub id: u64,
+    /// The tags of the clients shown in the tagset.
+    pub tags: Vec<String>,
+    /// The name of the layout arranging the tagset.
+    pub layout: String,
+    /// The index of the screen showing the tagset, if any.
+    pub screen: Option<usize>,
+    /// The client focused in the tagset.
+    pub focused: Option<u32>,
+    /// The root of the tagset's container tree.
+    pub tree: Node,
+}
+
+/// The data returned by a query.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Reply {
+    Screens(Vec<ScreenInfo>),
+    Clients(Vec<ClientInfo>),
+    Tagsets(Vec<TagsetInfo>),
+}
+
+/// The kind of error a request has failed with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// The request could not be parsed.
+    InvalidRequest,
+    /// An argument of the request is invalid, such as an unknown tag.
+    InvalidArgument,
+    /// The request operates on the focused container, but there is none.
+    NoFocus,
+    /// No container exists in the direction requested, or the item named doesn't exist.
+    NotFound,
+    /// The layout of the focused tagset doesn't support the operation requested.
+    Unsupported,
+}
+
+/// The response to a request.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Response {
+    /// The request succeeded, and returned data if it was a query.
+    Ok {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reply: Option<Reply>,
+    },
+    /// The request failed.
+    Error {
+        error: ErrorKind,
+        message: String,
+    },
+}
+
+impl Response {
+    /// Construct a response to a successful request.
+    pub fn ok(reply: Option<Reply>) -> Response {
+        Response::Ok { reply }
+    }
+
+    /// Construct a response to a failed request.
+    pub fn error<S: Into<String>>(error: ErrorKind, message: S) -> Response {
+        Response::Error { error, message: message.into() }
+    }
+}
+
+/// Get the path of the control socket.
+///
+/// Returns `None` if neither `$GWM_SOCKET` nor `$XDG_RUNTIME_DIR` is set.
+pub fn socket_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("GWM_SOCKET") {
+        return Some(PathBuf::from(path));
+    }
+
+    // `host:display.screen` is reduced to the display number
+    let display = env::var("DISPLAY").unwrap_or_default();
+    let display = display
+        .rsplit(':')
+        .next()
+        .and_then(|d| d.split('.').next())
+        .filter(|d| !d.is_empty())
+        .unwrap_or("0")
+        .to_owned();
+
+    env::var_os("XDG_RUNTIME_DIR")
+        .map(|dir| PathBuf::from(dir).join(format!("gwm-{}.sock", display)))
+}
+
+/// A line sent by the window manager.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Message {
+    /// The response to the oldest request not answered yet.
+    Response(Response),
+    /// An event the client has subscribed to.
+    Event(Event),
+}
+
+/// Encode a message as a line of JSON, including the terminating newline.
+pub fn encode<T: Serialize>(message: &T) -> String {
+    // none of the protocol's types contain maps with non-string keys, which can't fail
+    let mut line = serde_json::to_string(message).unwrap();
+    line.push('\n');
+    line
+}
+
+/// Decode a message from a line of JSON.
+pub fn decode<T: DeserializeOwned>(line: &str) -> IpcResult<T> {
+    serde_json::from_str(line).map_err(IpcError::InvalidMessage)
+}
+
+/// An error occured when talking on the control socket.
+#[derive(Debug)]
+pub enum IpcError {
+    /// Neither `$GWM_SOCKET` nor `$XDG_RUNTIME_DIR` is set.
+    NoSocketPath,
+    /// An I/O error occured on the socket.
+    IOError(io::Error),
+    /// The other side has closed the connection.
+    ConnectionClosed,
+    /// A line received is not a valid message.
+    InvalidMessage(serde_json::Error),
+}
+
+impl fmt::Display for IpcError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IpcError::NoSocketPath => write!(f, "XDG_RUNTIME_DIR is not set"),
+            IpcError::IOError(e) => write!(f, "{}", e),
+            IpcError::ConnectionClosed => write!(f, "connection closed"),
+            IpcError::InvalidMessage(e) => write!(f, "invalid message: {}", e),
+        }
+    }
+}
+
+impl From<io::Error> for IpcError {
+    fn from(e: io::Error) -> IpcError {
+        IpcError::IOError(e)
+    }
+}
+
+/// Result type for talking on the control socket.
+pub type IpcResult<T> = Result<T, IpcError>;
+
+/// A blocking connection to the window manager's control socket.
+#[derive(Debug)]
+pub struct Client {
+    /// The path of the socket connected to.
+    path: PathBuf,
+    /// The socket, read line by line.
+    stream: BufReader<UnixStream>,
+    /// Events received while waiting for a response.
+    events: VecDeque<Event>,
+}
+
+impl Client {
+    /// Connect to the control socket at its default location.
+    pub fn connect() -> IpcResult<Client> {
+        Client::connect_to(socket_path().ok_or(IpcError::NoSocketPath)?)
+    }
+
+    /// Connect to the control socket at the given path.
+    pub fn connect_to<P: Into<PathBuf>>(path: P) -> IpcResult<Client> {
+        let path = path.into();
+        let stream = UnixStream::connect(&path)?;
+
+        Ok(Client {
+            path,
+            stream: BufReader::new(stream),
+            events: VecDeque::new(),
+        })
+    }
+
+    /// Set a timeout for sending requests and waiting for responses.
+    ///
+    /// Operations timing out fail with an I/O error of kind `WouldBlock` or `TimedOut`.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) -> IpcResult<()> {
+        self.stream.get_ref().set_read_timeout(timeout)?;
+        self.stream.get_ref().set_write_timeout(timeout)?;
+        Ok(())
+    }
+
+    /// Get the path of the socket connected to.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Send a request without waiting for the response.
+    pub fn send(&mut self, request: &Request) -> IpcResult<()> {
+        self.stream.get_mut().write_all(encode(request).as_bytes())?;
+        Ok(())
+    }
+
+    /// Wait for the next message sent by the window manager.
+    pub fn receive(&mut self) -> IpcResult<Message> {
+        if let Some(event) = self.events.pop_front() {
+            return Ok(Message::Event(event));
+        }
+
+        let mut line = String::new();
+
+        match self.stream.read_line(&mut line)? {
+            0 => Err(IpcError::ConnectionClosed),
+            _ => decode(&line),
+        }
+    }
+
+    /// Send a request and wait for its response.
+    ///
+    /// Events received in the meantime are kept, and returned by later calls to `receive`
+    /// and `next_event`.
+    pub fn request(&mut self, request: &Request) -> IpcResult<Response> {
+        self.send(request)?;
+
+        loop {
+            let mut line = String::new();
+
+            if self.stream.read_line(&mut line)? == 0 {
+                return Err(IpcError::ConnectionClosed);
+            }
+
+            match decode(&line)? {
+                Message::Response(response) => return Ok(response),
+                Message::Event(event) => self.events.push_back(event),
+            }
+        }
+    }
+
+    /// Wait for the next event the connection is subscribed to.
+    ///
+    /// Responses received in the meantime are dropped.
+    pub fn next_event(&mut self) -> IpcResult<Event> {
+        loop {
+            if let Message::Event(event) = self.receive()? {
+                return Ok(event);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Debug;
+
+    use super::*;
+
+    fn round_trip<T>(message: T)
+        where T: Debug + PartialEq + Serialize + DeserializeOwned
+    {
+        let line = encode(&message);
+
+        assert!(line.ends_with('\n'));
+        assert_eq!(line.matches('\n').count(), 1);
+        assert_eq!(decode::<T>(&line).unwrap(), message);
+    }
+
+    #[test]
+    fn requests_round_trip() {
+        round_trip(Request::Focus { direction: Some(Direction::Left), criteria: None });
+        round_trip(Request::Focus { direction: None, criteria: Some("[tag=3]".to_owned()) });
+        round_trip(Request::View { tags: vec!["3".to_owned()], mode: TagMode::Toggle });
+        round_trip(Request::Layout { param: 0, value: -5, relative: true });
+        round_trip(Request::SetSplit { split: Split::Stacked });
+        round_trip(Request::Cycle { reverse: true, global: false });
+        round_trip(Request::Subscribe { events: vec![EventClass::Focus, EventClass::Title] });
+    }
+
+    #[test]
+    fn requests_use_defaults() {
+        assert_eq!(
+            decode::<Request>(r#"{"command":"view","tags":["1","2"]}"#).unwrap(),
+            Request::View { tags: vec!["1".to_owned(), "2".to_owned()], mode: TagMode::Set }
+        );
+        assert_eq!(
+            decode::<Request>(r#"{"command":"focus","direction":"up"}"#).unwrap(),
+            Request::Focus { direction: Some(Direction::Up), criteria: None }
+        );
+        assert_eq!(decode::<Request>(r#"{"command":"end_cycle"}"#).unwrap(), Request::EndCycle);
+        assert!(decode::<Request>(r#"{"command":"view"}"#).is_err());
+        assert!(decode::<Request>("view 1").is_err());
+    }
+
+    #[test]
+    fn responses_round_trip() {
+        let tree = Node::Split {
+            split: Split::Vertical,
+            floating: false,
+            mark: None,
+            children: vec![Node::Client { window: 7, floating: false, mark: Some("a".to_owned()) }],
+        };
+        let tagset = TagsetInfo {
+            id: 1,
+            tags: vec!["1".to_owned()],
+            layout: "manual".to_owned(),
+            screen: Some(0),
+            focused: Some(7),
+            tree,
+        };
+
+        round_trip(Response::ok(None));
+        round_trip(Response::ok(Some(Reply::Tagsets(vec![tagset]))));
+        round_trip(Response::error(ErrorKind::NotFound, "no such mark"));
+
+        assert_eq!(encode(&Response::ok(None)), "{\"status\":\"ok\"}\n");
+    }
+
+    #[test]
+    fn events_round_trip() {
+        let events = vec![
+            Event::Tagset { screen: 0, tags: vec!["1".to_owned(), "web".to_owned()] },
+            Event::Focus { screen: 1, window: None },
+            Event::Title { window: 7, title: "a \"title\"\n".to_owned() },
+            Event::Urgency { window: 7, urgent: true },
+            Event::Layout { screen: 0 },
+            Event::Screen { screen: 2, added: false },
+        ];
+
+        for event in events {
+            round_trip(event.clone());
+            round_trip(Message::Event(event));
+        }
+
+        assert_eq!(encode(&Event::Layout { screen: 0 }), "{\"event\":\"layout\",\"screen\":0}\n");
+    }
+
+    #[test]
+    fn messages_tell_events_from_responses() {
+        let event = decode::<Message>(r#"{"event":"focus","screen":0,"window":7}"#).unwrap();
+        let response = decode::<Message>(r#"{"status":"error","error":"no_focus","message":""}"#);
+
+        assert_eq!(event, Message::Event(Event::Focus { screen: 0, window: Some(7) }));
+        assert_eq!(
+            response.unwrap(),
+            Message::Response(Response::error(ErrorKind::NoFocus, ""))
+        );
+        assert!(decode::<Message>(r#"{"event":"unknown"}"#).is_err());
+    }
+}
@@ -40,8 +40,12 @@ use toml::Value;
 
 use xkb;
 
+use gwm_ipc::Request;
+
+use kbd::config;
 use kbd::err::*;
 use kbd::modmask;
+use kbd::wm::WmConnection;
 
 /// An index representing a mode.
 pub type Mode = usize;
@@ -62,16 +66,22 @@ pub enum CmdDesc {
     Shell(String),
     /// A mode to switch to.
     ModeSwitch(ModeSwitchDesc),
+    /// A request to send to the window manager.
+    Wm(Request),
 }
 
 impl CmdDesc {
     /// Run a command and possibly return an resulting mode switching action to perform.
-    pub fn run(&self) -> Option<ModeSwitchDesc> {
+    pub fn run(&self, wm: &mut WmConnection) -> Option<ModeSwitchDesc> {
         match *self {
             CmdDesc::Shell(ref repr) => {
                 let _ = Command::new("sh").args(&["-c", repr]).spawn();
                 None
             },
+            CmdDesc::Wm(ref request) => {
+                wm.send(request);
+                None
+            },
             CmdDesc::ModeSwitch(ref switch) => {
                 Some(*switch)
             },
@@ -79,11 +89,23 @@ impl CmdDesc {
     }
 
     /// Construct a command from a TOML value.
+    ///
+    /// Strings are passed to a shell, while tables of the form `{ wm = "focus left" }` hold a
+    /// request to send to the window manager, in the syntax accepted by `gwmc`.
     pub fn from_value(bind_str: String, value: Value) -> KbdResult<CmdDesc> {
-        if let Value::String(repr) = value {
-            Ok(CmdDesc::Shell(repr))
-        } else {
-            Err(KbdError::KeyTypeMismatch(bind_str, true))
+        match value {
+            Value::String(repr) => Ok(CmdDesc::Shell(repr)),
+            Value::Table(mut table) => {
+                let repr = match config::extract_string(&mut table, "wm") {
+                    Ok(repr) => repr,
+                    Err(_) => return Err(KbdError::KeyTypeMismatch(bind_str, true)),
+                };
+
+                repr.parse()
+                    .map(CmdDesc::Wm)
+                    .map_err(|e| KbdError::InvalidWmCommand(bind_str, e))
+            },
+            _ => Err(KbdError::KeyTypeMismatch(bind_str, true)),
         }
     }
 }
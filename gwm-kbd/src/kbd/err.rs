@@ -106,6 +106,8 @@ pub enum KbdError {
     KeysymCouldNotBeParsed(String),
     /// An invalid chord has been passed into the config.
     InvalidChord(String),
+    /// A window manager command bound to a chain could not be parsed.
+    InvalidWmCommand(String, String),
     /// An error encountered when interacting with X.
     X(XError),
 }
@@ -121,9 +123,10 @@ impl KbdError {
             TomlNotTable => error!("config is not a table at the top level"),
             KeyMissing(k) => error!("missing config key: {}", k),
             KeyTypeMismatch(k, false) => error!("key {} has incorrect type", k),
-            KeyTypeMismatch(k, true) => error!("command bound to `{}` has non-string type", k),
+            KeyTypeMismatch(k, true) => error!("command bound to `{}` has incorrect type", k),
             KeysymCouldNotBeParsed(k) => error!("could not parse keysym: {}", k),
             InvalidChord(d) => error!("chord invalid: {}", d),
+            InvalidWmCommand(k, e) => error!("command bound to `{}` is invalid: {}", k, e),
             X(e) => e.handle(),
         }
 
@@ -37,5 +37,6 @@ pub mod desc;
 pub mod err;
 pub mod state;
 pub mod modmask;
+pub mod wm;
 
 pub use self::err::*;
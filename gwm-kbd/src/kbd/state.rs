@@ -51,6 +51,7 @@ use kbd::config;
 use kbd::desc::*;
 use kbd::err::*;
 use kbd::modmask;
+use kbd::wm::WmConnection;
 
 /// Keyboard state object.
 pub struct KbdState<'a> {
@@ -221,6 +222,8 @@ pub struct DaemonState<'a> {
     last_keypress: Timestamp,
     /// The bindings registered in all modes.
     bindings: BTreeMap<(Mode, ChainDesc), CmdDesc>,
+    /// The connection to the window manager, used by commands sending requests to it.
+    wm: WmConnection,
 }
 
 impl<'a> DaemonState<'a> {
@@ -303,6 +306,7 @@ impl<'a> DaemonState<'a> {
             current_chain: ChainDesc::default(),
             last_keypress: 0,
             bindings,
+            wm: WmConnection::default(),
         })
     }
 
@@ -393,13 +397,13 @@ impl<'a> DaemonState<'a> {
         }
 
         if let Some(cmd) = self.modes[self.current_mode].leave_cmd() {
-            cmd.run();
+            cmd.run(&mut self.wm);
         }
 
         self.current_mode = new_mode;
 
         if let Some(cmd) = self.modes[self.current_mode].enter_cmd() {
-            cmd.run();
+            cmd.run(&mut self.wm);
         }
 
         self.ungrab_current_mode();
@@ -433,12 +437,14 @@ impl<'a> DaemonState<'a> {
 
         self.current_chain.push(chord);
 
+        let current_mode = self.current_mode;
+
         for (&(_, ref chain), cmd) in
-                self.bindings.iter().filter(|k| (k.0).0 == self.current_mode) {
+                self.bindings.iter().filter(|k| (k.0).0 == current_mode) {
             if self.current_chain.is_prefix_of(chain) {
                 if self.current_chain.len() == chain.len() {
                     info!("determined command {:?} from chain {:?}", cmd, self.current_chain);
-                    mode_switch = cmd.run();
+                    mode_switch = cmd.run(&mut self.wm);
 
                     drop_chain = true;
                     break;
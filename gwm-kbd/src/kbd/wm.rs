@@ -0,0 +1,108 @@
+/*
+ * Copyright Inokentiy Babushkin and contributors (c) 2016-2017
+ *
+ * All rights reserved.
+ *
+ * Redistribution and use in source and binary forms, with or without
+ * modification, are permitted provided that the following conditions
+ * are met:
+ *
+ *     * Redistributions of source code must retain the above copyright
+ *       notice, this list of conditions and the following disclaimer.
+ *
+ *     * Redistributions in binary form must reproduce the above
+ *       copyright notice, this list of conditions and the following
+ *       disclaimer in the documentation and/or other materials provided
+ *       with the distribution.
+ *
+ *     * Neither the name of Inokentiy Babushkin nor the names of other
+ *       contributors may be used to endorse or promote products derived
+ *       from this software without specific prior written permission.
+ *
+ * THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+ * "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+ * LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+ * A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+ * OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+ * SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+ * LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+ * DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+ * THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+ * (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+use std::io::ErrorKind;
+use std::time::Duration;
+
+use gwm_ipc::{Client, IpcError, IpcResult, Request, Response};
+
+/// The time to wait for the window manager to answer a request, in milliseconds.
+const REQUEST_TIMEOUT: u64 = 500;
+
+/// A persistent connection to the window manager's control socket.
+///
+/// The connection is established when the first request is sent, and reestablished after the
+/// window manager has been restarted.
+#[derive(Debug, Default)]
+pub struct WmConnection {
+    /// The connection, if established.
+    client: Option<Client>,
+}
+
+impl WmConnection {
+    /// Send a request to the window manager and wait for the response, logging failures.
+    pub fn send(&mut self, request: &Request) {
+        let reused = self.client.is_some();
+
+        let result = match self.try_send(request) {
+            // the window manager has closed the connection we've kept, so it has likely been
+            // restarted in the meantime
+            Err(ref e) if reused && is_disconnect(e) => {
+                info!("reconnecting to window manager");
+                self.try_send(request)
+            },
+            r => r,
+        };
+
+        match result {
+            Ok(Response::Ok { .. }) => (),
+            Ok(Response::Error { message, .. }) =>
+                error!("window manager refused request {:?}: {}", request, message),
+            Err(e) => error!("could not send request {:?} to window manager: {}", request, e),
+        }
+    }
+
+    /// Send a request, connecting first if necessary.
+    ///
+    /// The connection is dropped if any error occurs on it.
+    fn try_send(&mut self, request: &Request) -> IpcResult<Response> {
+        if self.client.is_none() {
+            let mut client = Client::connect()?;
+            client.set_timeout(Some(Duration::from_millis(REQUEST_TIMEOUT)))?;
+            debug!("connected to window manager at {}", client.path().display());
+
+            self.client = Some(client);
+        }
+
+        let result = self.client.as_mut().unwrap().request(request);
+
+        if result.is_err() {
+            self.client = None;
+        }
+
+        result
+    }
+}
+
+/// Check whether an error indicates that the other side has closed the connection.
+fn is_disconnect(error: &IpcError) -> bool {
+    match error {
+        IpcError::ConnectionClosed => true,
+        IpcError::IOError(e) => match e.kind() {
+            ErrorKind::BrokenPipe | ErrorKind::ConnectionReset | ErrorKind::NotConnected => true,
+            _ => false,
+        },
+        _ => false,
+    }
+}
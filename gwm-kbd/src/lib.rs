@@ -33,6 +33,7 @@
  */
 
 extern crate getopts;
+extern crate gwm_ipc;
 #[macro_use]
 extern crate log;
 extern crate toml;
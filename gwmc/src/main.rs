@@ -0,0 +1,222 @@
+extern crate getopts;
+extern crate gwm_ipc;
+
+use getopts::{Options, ParsingStyle};
+
+use std::env;
+use std::io::{self, Write};
+use std::process;
+
+use gwm_ipc::*;
+
+/// An error preventing a request from being answered.
+enum ClientError {
+    /// The command line doesn't describe a valid request.
+    Usage(String),
+    /// The control socket could not be used.
+    Socket(String),
+    /// The window manager answered with an error.
+    Failed(Response),
+}
+
+type ClientResult<T> = Result<T, ClientError>;
+
+/// Print a reply in a human-readable form.
+fn print_reply(reply: &Reply) {
+    fn rect(r: &Rect) -> String {
+        format!("{}x{}+{}+{}", r.width, r.height, r.x, r.y)
+    }
+
+    fn tree(node: &Node, focused: Option<u32>, depth: usize) {
+        let indent = "  ".repeat(depth);
+
+        match node {
+            Node::Split { split, floating, mark, children } => {
+                println!("{}{}{}{}",
+                         indent,
+                         format!("{:?}", split).to_lowercase(),
+                         if *floating { " floating" } else { "" },
+                         mark.as_ref().map_or_else(String::new, |m| format!(" mark={}", m)));
+
+                for child in children {
+                    tree(child, focused, depth + 1);
+                }
+            },
+            Node::Client { window, floating, mark } =>
+                println!("{}0x{:08x}{}{}{}",
+                         indent,
+                         window,
+                         if focused == Some(*window) { "*" } else { "" },
+                         if *floating { " floating" } else { "" },
+                         mark.as_ref().map_or_else(String::new, |m| format!(" mark={}", m))),
+        }
+    }
+
+    match reply {
+        Reply::Screens(screens) => for screen in screens {
+            println!("{}{} {} area={} tagset={} tags={}",
+                     screen.index,
+                     if screen.focused { "*" } else { "" },
+                     rect(&screen.geometry),
+                     rect(&screen.area),
+                     screen.tagset,
+                     screen.tags.join(","));
+        },
+        Reply::Clients(clients) => for client in clients {
+            println!("0x{:08x}{} [{}] {} {:?}{}{}",
+                     client.window,
+                     if client.focused { "*" } else { "" },
+                     client.tags.join(","),
+                     client.class.as_ref().map_or("-", |c| c.as_str()),
+                     client.title,
+                     if client.urgent { " urgent" } else { "" },
+                     if client.fullscreen.is_some() { " fullscreen" } else { "" });
+        },
+        Reply::Tagsets(tagsets) => for tagset in tagsets {
+            println!("{} [{}] {}{}",
+                     tagset.id,
+                     tagset.tags.join(","),
+                     tagset.layout,
+                     tagset.screen.map_or_else(String::new, |s| format!(" screen={}", s)));
+            tree(&tagset.tree, tagset.focused, 1);
+        },
+    }
+}
+
+/// Print an event in a human-readable form.
+fn print_event(event: &Event) {
+    match event {
+        Event::Tagset { screen, tags } => println!("tagset {} {}", screen, tags.join(",")),
+        Event::Focus { screen, window: Some(window) } =>
+            println!("focus {} 0x{:08x}", screen, window),
+        Event::Focus { screen, window: None } => println!("focus {} none", screen),
+        Event::Title { window, title } => println!("title 0x{:08x} {:?}", window, title),
+        Event::Urgency { window, urgent } => println!("urgency 0x{:08x} {}", window, urgent),
+        Event::Layout { screen } => println!("layout {}", screen),
+        Event::Screen { screen, added } =>
+            println!("screen {} {}", screen, if *added { "added" } else { "removed" }),
+    }
+}
+
+/// Wrap an error occured on a connection to the window manager.
+fn socket_error(client: &Client, e: IpcError) -> ClientError {
+    ClientError::Socket(format!("{}: {}", client.path().display(), e))
+}
+
+/// Print the events received until the connection is closed.
+fn follow(client: &mut Client, json: bool) -> ClientResult<()> {
+    loop {
+        let event = client.next_event().map_err(|e| socket_error(client, e))?;
+
+        if json {
+            print!("{}", encode(&event));
+        } else {
+            print_event(&event);
+        }
+
+        // bars read our output through a pipe
+        io::stdout().flush().map_err(|e| ClientError::Socket(e.to_string()))?;
+    }
+}
+
+/// Send the request described by the command line and print the response.
+fn run(args: &[String], socket: Option<String>, json: bool) -> ClientResult<()> {
+    let request = parse_command(args).map_err(ClientError::Usage)?;
+
+    let connected = match socket {
+        Some(path) => Client::connect_to(path),
+        None => Client::connect(),
+    };
+
+    let mut client = connected.map_err(|e| ClientError::Socket(e.to_string()))?;
+    let response = client.request(&request).map_err(|e| socket_error(&client, e))?;
+
+    if json {
+        print!("{}", encode(&response));
+    }
+
+    match response {
+        Response::Ok { reply: Some(ref reply) } if !json => print_reply(reply),
+        Response::Ok { .. } => (),
+        r => return Err(ClientError::Failed(r)),
+    }
+
+    if let Request::Subscribe { .. } = request {
+        follow(&mut client, json)?;
+    }
+
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    let mut opts = Options::new();
+    // everything after the command is passed on, so that `view -3` works
+    opts.parsing_style(ParsingStyle::StopAtFirstFree);
+    opts.optflag("j", "json", "print responses as JSON");
+    opts.optopt("s", "socket", "set control socket path", "PATH");
+    opts.optflag("h", "help", "print this help menu");
+
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(f) => {
+            eprintln!("{}: {}", args[0], f);
+            process::exit(2);
+        },
+    };
+
+    if matches.opt_present("h") {
+        let brief = format!("Usage: {} [options] [CRITERIA] COMMAND [ARGS...]\n\n\
+                            Commands:\n    \
+                            focus left|up|right|down|next|prev|urgent\n    \
+                            focus window|screen|tagset ID\n    \
+                            focus mark NAME\n    \
+                            focus previous [global]\n    \
+                            cycle next|prev [global]\n    \
+                            cycle end\n    \
+                            jump QUERY...\n    \
+                            move|swap left|up|right|down|next|prev\n    \
+                            move|swap mark NAME\n    \
+                            mark|unmark NAME\n    \
+                            split horizontal|vertical|tabbed|stacked\n    \
+                            float\n    \
+                            fullscreen [screen|container]\n    \
+                            close\n    \
+                            tag|view [+|-|^]TAG...\n    \
+                            layout horizontal|vertical|tabbed|stacked|toggle\n    \
+                            layout msg PARAM [+|-]VALUE\n    \
+                            scratchpad toggle|move NAME\n    \
+                            get screens|clients|tagsets\n    \
+                            subscribe [tagset|focus|title|urgency|layout|screen...]\n\n\
+                            The focus, float, close, tag and scratchpad move commands can be\n\
+                            prefixed with criteria selecting the clients to act on, such as\n    \
+                            [class=\"Firefox\" title=\"~.*Meet.*\" tag=3] focus",
+                            &args[0]);
+        eprintln!("{}", opts.usage(&brief));
+        return;
+    }
+
+    let json = matches.opt_present("j");
+
+    match run(&matches.free, matches.opt_str("s"), json) {
+        Ok(()) => (),
+        Err(ClientError::Usage(message)) => {
+            eprintln!("{}: {}, see --help", args[0], message);
+            process::exit(2);
+        },
+        Err(ClientError::Socket(message)) => {
+            eprintln!("{}: could not talk to the window manager: {}", args[0], message);
+            process::exit(1);
+        },
+        Err(ClientError::Failed(response)) => {
+            if let Response::Error { message, .. } = response {
+                if !json {
+                    eprintln!("{}: {}", args[0], message);
+                }
+            }
+
+            process::exit(1);
+        },
+    }
+}